@@ -1,5 +1,13 @@
+// El macro #[program] de anchor 0.31 usa AccountInfo::realloc (deprecado)
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Mint};
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::slot_hashes::MAX_ENTRIES;
+use anchor_lang::solana_program::sysvar::slot_hashes;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::spl_token_2022::instruction::AuthorityType;
+use anchor_spl::token_interface::{self, Mint, MintTo, SetAuthority, TokenAccount, TokenInterface};
 
 declare_id!("GJQx7NjqkrE5nRfQAeEEmpZ9eHH3NiAvCv9CXjkEpYyx");
 
@@ -13,78 +21,340 @@ pub mod pokemon_game {
         game_state.admin = ctx.accounts.admin.key();
         game_state.total_packs_opened = 0;
         game_state.total_pokemon_minted = 0;
+        game_state.pity_threshold = 50; // Legendary garantizado en el pack premium 50 sin Legendary
+        game_state.pity_soft_start = 40; // desde acá suben las chances de Legendary
         
         msg!("Pokemon Game initialized by admin: {}", ctx.accounts.admin.key());
         Ok(())
     }
 
-    // Función para abrir pack GRATUITO (Starter Pack)
-    pub fn open_starter_pack(ctx: Context<OpenStarterPack>) -> Result<()> {
-        let user_state = &mut ctx.accounts.user_state;
+    // Configurar el pity de los packs premium (solo admin)
+    pub fn set_pity_config(ctx: Context<SetPityConfig>, pity_threshold: u32, pity_soft_start: u32) -> Result<()> {
+        require!(pity_threshold > 0 && pity_soft_start < pity_threshold, ErrorCode::InvalidPityConfig);
+        
         let game_state = &mut ctx.accounts.game_state;
+        game_state.pity_threshold = pity_threshold;
+        game_state.pity_soft_start = pity_soft_start;
+        
+        msg!("Pity config updated: guaranteed at {}, soft start at {}", pity_threshold, pity_soft_start);
+        Ok(())
+    }
+
+    // Crear la tabla de drops de un tipo de pack (solo admin)
+    pub fn create_drop_table(
+        ctx: Context<CreateDropTable>,
+        is_premium: bool,
+        entries: Vec<DropEntry>,
+    ) -> Result<()> {
+        validate_drop_entries(&entries)?;
+        
+        let drop_table = &mut ctx.accounts.drop_table;
+        drop_table.is_premium = is_premium;
+        drop_table.entries = entries;
+        drop_table.bump = ctx.bumps.drop_table;
+        
+        msg!("Drop table created with {} entries (premium: {})", drop_table.entries.len(), is_premium);
+        Ok(())
+    }
+
+    // Reemplazar las entradas de una tabla de drops, p. ej. para una temporada (solo admin)
+    pub fn update_drop_table(ctx: Context<UpdateDropTable>, entries: Vec<DropEntry>) -> Result<()> {
+        validate_drop_entries(&entries)?;
+        
+        let drop_table = &mut ctx.accounts.drop_table;
+        drop_table.entries = entries;
+        
+        msg!("Drop table updated with {} entries (premium: {})", drop_table.entries.len(), drop_table.is_premium);
+        Ok(())
+    }
+
+    // Paso 1 de la apertura: el usuario se compromete con hash(secreto).
+    // El pack premium se cobra y el starter se consume acá, para que no se pueda
+    // abandonar un mal resultado y volver a tirar.
+    pub fn commit_pack(ctx: Context<CommitPack>, commitment: [u8; 32], is_premium: bool) -> Result<()> {
+        let user_state = &mut ctx.accounts.user_state;
+        
+        if is_premium {
+            // Verificar que el usuario tenga suficientes PokéCoins (50 USDC = 5000 PokéCoins)
+            require!(user_state.poke_coins >= 5000, ErrorCode::InsufficientFunds);
+            user_state.poke_coins -= 5000; // Cobrar el pack
+        } else {
+            // Verificar que el usuario no haya abierto pack gratuito antes
+            require!(!user_state.has_opened_starter, ErrorCode::StarterPackAlreadyOpened);
+            user_state.has_opened_starter = true; // Consumir el starter
+        }
         
-        // Verificar que el usuario no haya abierto pack gratuito antes
-        require!(!user_state.has_opened_starter, ErrorCode::StarterPackAlreadyOpened);
+        let pack_commit = &mut ctx.accounts.pack_commit;
+        pack_commit.user = ctx.accounts.user.key();
+        pack_commit.commitment = commitment;
+        pack_commit.commit_slot = Clock::get()?.slot;
+        pack_commit.is_premium = is_premium;
+        pack_commit.pack_count = 1;
+        pack_commit.bump = ctx.bumps.pack_commit;
         
-        // Generar Pokémon aleatorio (simulado con timestamp)
+        msg!("Pack commit registered at slot {}", pack_commit.commit_slot);
+        Ok(())
+    }
+
+    // Paso 1 de la apertura múltiple: un solo commit y un solo cobro por `count` packs premium
+    pub fn commit_premium_packs(ctx: Context<CommitPack>, commitment: [u8; 32], count: u8) -> Result<()> {
+        require!(count >= 1 && count as usize <= MAX_BULK_PACKS, ErrorCode::InvalidPackCount);
+        
+        let user_state = &mut ctx.accounts.user_state;
+        let price = 5000 * count as u64;
+        
+        require!(user_state.poke_coins >= price, ErrorCode::InsufficientFunds);
+        user_state.poke_coins -= price; // Cobrar todos los packs
+        
+        let pack_commit = &mut ctx.accounts.pack_commit;
+        pack_commit.user = ctx.accounts.user.key();
+        pack_commit.commitment = commitment;
+        pack_commit.commit_slot = Clock::get()?.slot;
+        pack_commit.is_premium = true;
+        pack_commit.pack_count = count;
+        pack_commit.bump = ctx.bumps.pack_commit;
+        
+        msg!("Commit for {} premium packs registered at slot {}", count, pack_commit.commit_slot);
+        Ok(())
+    }
+
+    // Cerrar un commit que ya no se puede revelar (su slot salió de SlotHashes).
+    // No hay reembolso: si lo hubiera, se podría esperar a ver el resultado y abandonar.
+    pub fn close_expired_commit(ctx: Context<CloseExpiredCommit>) -> Result<()> {
+        let pack_commit = &ctx.accounts.pack_commit;
+        let current_slot = Clock::get()?.slot;
+        
+        require!(
+            current_slot > pack_commit.commit_slot + MAX_ENTRIES as u64,
+            ErrorCode::CommitNotExpired
+        );
+        
+        msg!("Expired pack commit closed for user: {}", pack_commit.user);
+        Ok(())
+    }
+
+    // Función para abrir pack GRATUITO (Starter Pack)
+    // (el starter ya se consumió en commit_pack)
+    pub fn open_starter_pack(ctx: Context<OpenStarterPack>, secret: [u8; 32]) -> Result<()> {
+        // Generar Pokémon aleatorio revelando el commit
         let clock = Clock::get()?;
-        let random_seed = clock.unix_timestamp as u64;
+        let random_seed = reveal_pack_seed(
+            &ctx.accounts.pack_commit,
+            &secret,
+            &ctx.accounts.slot_hashes,
+            clock.slot,
+        )?;
+        
+        let pokemon = generate_pokemon(random_seed, &ctx.accounts.drop_table, LegendaryOdds::Boosted(1))?;
         
-        let pokemon = generate_pokemon(random_seed, false)?; // false = no es pack premium
+        // Mintear el NFT y guardar los datos del Pokémon
+        mint_pack_pokemon(
+            &ctx.accounts.game_state,
+            ctx.bumps.game_state,
+            &ctx.accounts.pokemon_mint,
+            &ctx.accounts.user_pokemon_account,
+            &ctx.accounts.token_program,
+        )?;
+        
+        let pokemon_nft = &mut ctx.accounts.pokemon_nft;
+        pokemon_nft.owner = ctx.accounts.user.key();
+        pokemon_nft.mint = ctx.accounts.pokemon_mint.key();
+        pokemon_nft.pokemon = pokemon.clone();
+        pokemon_nft.pack_type = "Starter".to_string();
+        pokemon_nft.obtained_at = clock.unix_timestamp;
+        pokemon_nft.bump = ctx.bumps.pokemon_nft;
         
         // Actualizar estados
-        user_state.has_opened_starter = true;
+        let user_state = &mut ctx.accounts.user_state;
+        let game_state = &mut ctx.accounts.game_state;
+        
         user_state.pokemon_count += 1;
         user_state.total_packs_opened += 1;
         
         game_state.total_packs_opened += 1;
         game_state.total_pokemon_minted += 1;
         
+        msg!("Starter pack opened! Pokemon: {} (Rarity: {})", pokemon.name, pokemon.rarity);
+        
         // Emitir evento
         emit!(PokemonObtained {
             user: ctx.accounts.user.key(),
+            pokemon_mint: ctx.accounts.pokemon_mint.key(),
             pokemon_id: pokemon.id,
             pokemon_name: pokemon.name,
             rarity: pokemon.rarity,
             pack_type: "Starter".to_string(),
+            pity_counter: user_state.premium_packs_since_legendary,
+            packs_until_guaranteed: game_state.pity_threshold.saturating_sub(user_state.premium_packs_since_legendary),
         });
         
-        msg!("Starter pack opened! Pokemon: {} (Rarity: {})", pokemon.name, pokemon.rarity);
         Ok(())
     }
 
     // Función para abrir pack PREMIUM (50 USDC)
-    pub fn open_premium_pack(ctx: Context<OpenPremiumPack>) -> Result<()> {
-        let user_state = &mut ctx.accounts.user_state;
-        let game_state = &mut ctx.accounts.game_state;
+    // (los 5000 PokéCoins ya se cobraron en commit_pack)
+    pub fn open_premium_pack(ctx: Context<OpenPremiumPack>, secret: [u8; 32]) -> Result<()> {
+        // Generar Pokémon aleatorio premium revelando el commit
+        let clock = Clock::get()?;
+        let random_seed = reveal_pack_seed(
+            &ctx.accounts.pack_commit,
+            &secret,
+            &ctx.accounts.slot_hashes,
+            clock.slot,
+        )?;
         
-        // Verificar que el usuario tenga suficientes PokéCoins (50 USDC = 5000 PokéCoins)
-        require!(user_state.poke_coins >= 5000, ErrorCode::InsufficientFunds);
+        // Pity: subir las chances de Legendary tras muchos packs sin uno, y garantizarlo en el umbral
+        let odds = legendary_odds(ctx.accounts.user_state.premium_packs_since_legendary, &ctx.accounts.game_state);
+        let pokemon = generate_pokemon(random_seed, &ctx.accounts.drop_table, odds)?;
         
-        // Generar Pokémon aleatorio premium
-        let clock = Clock::get()?;
-        let random_seed = clock.unix_timestamp as u64;
+        // Mintear el NFT y guardar los datos del Pokémon
+        mint_pack_pokemon(
+            &ctx.accounts.game_state,
+            ctx.bumps.game_state,
+            &ctx.accounts.pokemon_mint,
+            &ctx.accounts.user_pokemon_account,
+            &ctx.accounts.token_program,
+        )?;
         
-        let pokemon = generate_pokemon(random_seed, true)?; // true = pack premium
+        let pokemon_nft = &mut ctx.accounts.pokemon_nft;
+        pokemon_nft.owner = ctx.accounts.user.key();
+        pokemon_nft.mint = ctx.accounts.pokemon_mint.key();
+        pokemon_nft.pokemon = pokemon.clone();
+        pokemon_nft.pack_type = "Premium".to_string();
+        pokemon_nft.obtained_at = clock.unix_timestamp;
+        pokemon_nft.bump = ctx.bumps.pokemon_nft;
         
         // Actualizar estados
-        user_state.poke_coins -= 5000; // Cobrar el pack
+        let user_state = &mut ctx.accounts.user_state;
+        let game_state = &mut ctx.accounts.game_state;
+        
         user_state.pokemon_count += 1;
         user_state.total_packs_opened += 1;
+        if pokemon.rarity == Rarity::Legendary.as_str() {
+            user_state.premium_packs_since_legendary = 0;
+        } else {
+            user_state.premium_packs_since_legendary += 1;
+        }
         
         game_state.total_packs_opened += 1;
         game_state.total_pokemon_minted += 1;
         
+        msg!("Premium pack opened! Pokemon: {} (Rarity: {})", pokemon.name, pokemon.rarity);
+        
         // Emitir evento
         emit!(PokemonObtained {
             user: ctx.accounts.user.key(),
+            pokemon_mint: ctx.accounts.pokemon_mint.key(),
             pokemon_id: pokemon.id,
             pokemon_name: pokemon.name,
             rarity: pokemon.rarity,
             pack_type: "Premium".to_string(),
+            pity_counter: user_state.premium_packs_since_legendary,
+            packs_until_guaranteed: game_state.pity_threshold.saturating_sub(user_state.premium_packs_since_legendary),
+        });
+        
+        Ok(())
+    }
+
+    // Abrir varios packs premium con una sola semilla verificable. Los resultados quedan
+    // guardados en PendingPulls y cada NFT se mintea después con claim_pending_pull.
+    pub fn open_premium_packs(ctx: Context<OpenPremiumPacks>, secret: [u8; 32]) -> Result<()> {
+        let count = ctx.accounts.pack_commit.pack_count as usize;
+        require!(
+            ctx.accounts.pending_pulls.pulls.len() + count <= MAX_BULK_PACKS,
+            ErrorCode::TooManyPendingPulls
+        );
+        
+        let clock = Clock::get()?;
+        let random_seed = reveal_pack_seed(
+            &ctx.accounts.pack_commit,
+            &secret,
+            &ctx.accounts.slot_hashes,
+            clock.slot,
+        )?;
+        
+        let user_state = &mut ctx.accounts.user_state;
+        let game_state = &mut ctx.accounts.game_state;
+        let pending_pulls = &mut ctx.accounts.pending_pulls;
+        pending_pulls.user = ctx.accounts.user.key();
+        pending_pulls.bump = ctx.bumps.pending_pulls;
+        
+        let mut pokemon_ids = Vec::with_capacity(count);
+        let mut rarities = Vec::with_capacity(count);
+        
+        for i in 0..count {
+            // Cada tirada usa hash(semilla || i), así todas se pueden recalcular desde PackRevealed
+            let roll_seed = hashv(&[&random_seed.to_le_bytes(), &[i as u8]]).to_bytes();
+            let roll_seed = u64::from_le_bytes(roll_seed[..8].try_into().unwrap());
+            
+            let odds = legendary_odds(user_state.premium_packs_since_legendary, game_state);
+            let pokemon = generate_pokemon(roll_seed, &ctx.accounts.drop_table, odds)?;
+            
+            if pokemon.rarity == Rarity::Legendary.as_str() {
+                user_state.premium_packs_since_legendary = 0;
+            } else {
+                user_state.premium_packs_since_legendary += 1;
+            }
+            
+            pokemon_ids.push(pokemon.id);
+            rarities.push(pokemon.rarity.clone());
+            pending_pulls.pulls.push(pokemon);
+        }
+        
+        user_state.total_packs_opened += count as u32;
+        game_state.total_packs_opened += count as u64;
+        
+        msg!("{} premium packs opened!", count);
+        
+        emit!(PokemonBatchObtained {
+            user: ctx.accounts.user.key(),
+            pokemon_ids,
+            rarities,
+            pity_counter: user_state.premium_packs_since_legendary,
+            packs_until_guaranteed: game_state.pity_threshold.saturating_sub(user_state.premium_packs_since_legendary),
+        });
+        
+        Ok(())
+    }
+
+    // Mintear el NFT de un resultado guardado por open_premium_packs
+    pub fn claim_pending_pull(ctx: Context<ClaimPendingPull>) -> Result<()> {
+        let pokemon = ctx.accounts.pending_pulls.pulls.pop().ok_or(ErrorCode::NoPendingPulls)?;
+        
+        // Mintear el NFT y guardar los datos del Pokémon
+        mint_pack_pokemon(
+            &ctx.accounts.game_state,
+            ctx.bumps.game_state,
+            &ctx.accounts.pokemon_mint,
+            &ctx.accounts.user_pokemon_account,
+            &ctx.accounts.token_program,
+        )?;
+        
+        let pokemon_nft = &mut ctx.accounts.pokemon_nft;
+        pokemon_nft.owner = ctx.accounts.user.key();
+        pokemon_nft.mint = ctx.accounts.pokemon_mint.key();
+        pokemon_nft.pokemon = pokemon.clone();
+        pokemon_nft.pack_type = "Premium".to_string();
+        pokemon_nft.obtained_at = Clock::get()?.unix_timestamp;
+        pokemon_nft.bump = ctx.bumps.pokemon_nft;
+        
+        ctx.accounts.user_state.pokemon_count += 1;
+        ctx.accounts.game_state.total_pokemon_minted += 1;
+        
+        msg!("Pending pull claimed! Pokemon: {} (Rarity: {})", pokemon.name, pokemon.rarity);
+        
+        emit!(PokemonObtained {
+            user: ctx.accounts.user.key(),
+            pokemon_mint: ctx.accounts.pokemon_mint.key(),
+            pokemon_id: pokemon.id,
+            pokemon_name: pokemon.name,
+            rarity: pokemon.rarity,
+            pack_type: "Premium".to_string(),
+            pity_counter: ctx.accounts.user_state.premium_packs_since_legendary,
+            packs_until_guaranteed: ctx.accounts.game_state.pity_threshold
+                .saturating_sub(ctx.accounts.user_state.premium_packs_since_legendary),
         });
         
-        msg!("Premium pack opened! Pokemon: {} (Rarity: {})", pokemon.name, pokemon.rarity);
         Ok(())
     }
 
@@ -97,6 +367,7 @@ pub mod pokemon_game {
         user_state.total_packs_opened = 0;
         user_state.has_opened_starter = false;
         user_state.level = 1;
+        user_state.premium_packs_since_legendary = 0;
         
         msg!("User initialized: {}", ctx.accounts.user.key());
         Ok(())
@@ -110,6 +381,8 @@ pub struct GameState {
     pub admin: Pubkey,
     pub total_packs_opened: u64,
     pub total_pokemon_minted: u64,
+    pub pity_threshold: u32,
+    pub pity_soft_start: u32,
 }
 
 #[account]
@@ -120,15 +393,99 @@ pub struct UserState {
     pub total_packs_opened: u32,
     pub has_opened_starter: bool,
     pub level: u32,
+    pub premium_packs_since_legendary: u32,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
 pub struct Pokemon {
     pub id: u32,
+    #[max_len(20)]
     pub name: String,
+    #[max_len(10)]
     pub rarity: String,
+    #[max_len(10)]
+    pub type1: String,
+    #[max_len(10)]
+    pub type2: Option<String>,
+}
+
+pub const MAX_DROP_ENTRIES: usize = 32;
+
+// Tabla de drops de un tipo de pack: cada entrada sale con probabilidad weight / suma de weights
+#[account]
+#[derive(InitSpace)]
+pub struct DropTable {
+    pub is_premium: bool,
+    #[max_len(MAX_DROP_ENTRIES)]
+    pub entries: Vec<DropEntry>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct DropEntry {
+    pub species_id: u32,
+    #[max_len(20)]
+    pub name: String,
+    pub rarity: Rarity,
+    #[max_len(10)]
     pub type1: String,
+    #[max_len(10)]
     pub type2: Option<String>,
+    pub weight: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum Rarity {
+    Common,
+    Shiny,
+    Legendary,
+}
+
+impl Rarity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rarity::Common => "Common",
+            Rarity::Shiny => "Shiny",
+            Rarity::Legendary => "Legendary",
+        }
+    }
+}
+
+// Commit pendiente de apertura de pack (uno por usuario)
+#[account]
+#[derive(InitSpace)]
+pub struct PackCommit {
+    pub user: Pubkey,
+    pub commitment: [u8; 32], // hash(secreto)
+    pub commit_slot: u64,
+    pub is_premium: bool,
+    pub pack_count: u8,
+    pub bump: u8,
+}
+
+pub const MAX_BULK_PACKS: usize = 10;
+
+// Resultados de open_premium_packs que todavía no se mintearon
+#[account]
+#[derive(InitSpace)]
+pub struct PendingPulls {
+    pub user: Pubkey,
+    #[max_len(MAX_BULK_PACKS)]
+    pub pulls: Vec<Pokemon>,
+    pub bump: u8,
+}
+
+// NFT obtenido de un pack: un mint de 0 decimales + esta cuenta con los datos
+#[account]
+#[derive(InitSpace)]
+pub struct PokemonNft {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub pokemon: Pokemon,
+    #[max_len(10)]
+    pub pack_type: String,
+    pub obtained_at: i64,
+    pub bump: u8,
 }
 
 // CONTEXTOS (QUÉ CUENTAS NECESITA CADA FUNCIÓN)
@@ -138,7 +495,7 @@ pub struct InitializeGame<'info> {
     #[account(
         init,
         payer = admin,
-        space = 8 + 32 + 8 + 8, // discriminator + pubkey + u64 + u64
+        space = 8 + 32 + 8 + 8 + 4 + 4, // discriminator + pubkey + u64 + u64 + u32 + u32
         seeds = [b"game_state"],
         bump
     )]
@@ -153,7 +510,7 @@ pub struct InitializeUser<'info> {
     #[account(
         init,
         payer = user,
-        space = 8 + 32 + 8 + 4 + 4 + 1 + 4, // discriminator + pubkey + u64 + u32 + u32 + bool + u32
+        space = 8 + 32 + 8 + 4 + 4 + 1 + 4 + 4, // discriminator + pubkey + u64 + u32 + u32 + bool + u32 + u32
         seeds = [b"user_state", user.key().as_ref()],
         bump
     )]
@@ -163,6 +520,91 @@ pub struct InitializeUser<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct SetPityConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state"],
+        bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub game_state: Account<'info, GameState>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(is_premium: bool)]
+pub struct CreateDropTable<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + DropTable::INIT_SPACE,
+        seeds = [b"drop_table".as_ref(), &[is_premium as u8]],
+        bump
+    )]
+    pub drop_table: Account<'info, DropTable>,
+    #[account(
+        seeds = [b"game_state"],
+        bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateDropTable<'info> {
+    #[account(
+        mut,
+        seeds = [b"drop_table".as_ref(), &[drop_table.is_premium as u8]],
+        bump = drop_table.bump
+    )]
+    pub drop_table: Account<'info, DropTable>,
+    #[account(
+        seeds = [b"game_state"],
+        bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub game_state: Account<'info, GameState>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CommitPack<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + PackCommit::INIT_SPACE,
+        seeds = [b"pack_commit", user.key().as_ref()],
+        bump
+    )]
+    pub pack_commit: Account<'info, PackCommit>,
+    #[account(
+        mut,
+        seeds = [b"user_state", user.key().as_ref()],
+        bump
+    )]
+    pub user_state: Account<'info, UserState>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseExpiredCommit<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"pack_commit", user.key().as_ref()],
+        bump = pack_commit.bump
+    )]
+    pub pack_commit: Account<'info, PackCommit>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct OpenStarterPack<'info> {
     #[account(
@@ -177,7 +619,51 @@ pub struct OpenStarterPack<'info> {
         bump
     )]
     pub game_state: Account<'info, GameState>,
+    #[account(
+        seeds = [b"drop_table".as_ref(), &[0]],
+        bump = drop_table.bump
+    )]
+    pub drop_table: Account<'info, DropTable>,
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"pack_commit", user.key().as_ref()],
+        bump = pack_commit.bump,
+        constraint = !pack_commit.is_premium @ ErrorCode::WrongPackType
+    )]
+    pub pack_commit: Account<'info, PackCommit>,
+    /// CHECK: sysvar SlotHashes, se lee a mano porque es demasiado grande para deserializar
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + PokemonNft::INIT_SPACE,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump
+    )]
+    pub pokemon_nft: Account<'info, PokemonNft>,
+    #[account(
+        init,
+        payer = user,
+        mint::decimals = 0,
+        mint::authority = game_state,
+        mint::token_program = token_program,
+    )]
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
     pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
@@ -194,87 +680,340 @@ pub struct OpenPremiumPack<'info> {
         bump
     )]
     pub game_state: Account<'info, GameState>,
+    #[account(
+        seeds = [b"drop_table".as_ref(), &[1]],
+        bump = drop_table.bump
+    )]
+    pub drop_table: Account<'info, DropTable>,
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"pack_commit", user.key().as_ref()],
+        bump = pack_commit.bump,
+        constraint = pack_commit.is_premium && pack_commit.pack_count == 1 @ ErrorCode::WrongPackType
+    )]
+    pub pack_commit: Account<'info, PackCommit>,
+    /// CHECK: sysvar SlotHashes, se lee a mano porque es demasiado grande para deserializar
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + PokemonNft::INIT_SPACE,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump
+    )]
+    pub pokemon_nft: Account<'info, PokemonNft>,
+    #[account(
+        init,
+        payer = user,
+        mint::decimals = 0,
+        mint::authority = game_state,
+        mint::token_program = token_program,
+    )]
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
     pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenPremiumPacks<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_state", user.key().as_ref()],
+        bump
+    )]
+    pub user_state: Account<'info, UserState>,
+    #[account(
+        mut,
+        seeds = [b"game_state"],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    #[account(
+        seeds = [b"drop_table".as_ref(), &[1]],
+        bump = drop_table.bump
+    )]
+    pub drop_table: Account<'info, DropTable>,
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"pack_commit", user.key().as_ref()],
+        bump = pack_commit.bump,
+        constraint = pack_commit.is_premium @ ErrorCode::WrongPackType
+    )]
+    pub pack_commit: Account<'info, PackCommit>,
+    /// CHECK: sysvar SlotHashes, se lee a mano porque es demasiado grande para deserializar
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + PendingPulls::INIT_SPACE,
+        seeds = [b"pending_pulls", user.key().as_ref()],
+        bump
+    )]
+    pub pending_pulls: Account<'info, PendingPulls>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPendingPull<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_state", user.key().as_ref()],
+        bump
+    )]
+    pub user_state: Account<'info, UserState>,
+    #[account(
+        mut,
+        seeds = [b"game_state"],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    #[account(
+        mut,
+        seeds = [b"pending_pulls", user.key().as_ref()],
+        bump = pending_pulls.bump
+    )]
+    pub pending_pulls: Account<'info, PendingPulls>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + PokemonNft::INIT_SPACE,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump
+    )]
+    pub pokemon_nft: Account<'info, PokemonNft>,
+    #[account(
+        init,
+        payer = user,
+        mint::decimals = 0,
+        mint::authority = game_state,
+        mint::token_program = token_program,
+    )]
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
 }
 
 // EVENTOS
 #[event]
 pub struct PokemonObtained {
     pub user: Pubkey,
+    pub pokemon_mint: Pubkey,
     pub pokemon_id: u32,
     pub pokemon_name: String,
     pub rarity: String,
     pub pack_type: String,
+    pub pity_counter: u32, // packs premium seguidos sin Legendary
+    pub packs_until_guaranteed: u32,
+}
+
+#[event]
+pub struct PackRevealed {
+    pub user: Pubkey,
+    pub commitment: [u8; 32],
+    pub secret: [u8; 32],
+    pub commit_slot: u64,
+    pub slot_hash: [u8; 32],
+    pub random_seed: u64,
+}
+
+#[event]
+pub struct PokemonBatchObtained {
+    pub user: Pubkey,
+    pub pokemon_ids: Vec<u32>,
+    pub rarities: Vec<String>,
+    pub pity_counter: u32,
+    pub packs_until_guaranteed: u32,
 }
 
 // FUNCIONES AUXILIARES
-fn generate_pokemon(seed: u64, is_premium: bool) -> Result<Pokemon> {
-    // Lista de Pokémon disponibles
-    let pokemon_list = [
-        ("Charmander", "Fire", None, "Common"),
-        ("Squirtle", "Water", None, "Common"),
-        ("Bulbasaur", "Grass", Some("Poison"), "Common"),
-        ("Pikachu", "Electric", None, "Common"),
-        ("Jigglypuff", "Normal", Some("Fairy"), "Common"),
-        ("Psyduck", "Water", None, "Common"),
-        ("Machop", "Fighting", None, "Common"),
-        ("Geodude", "Rock", Some("Ground"), "Common"),
-        ("Magikarp", "Water", None, "Common"),
-        ("Eevee", "Normal", None, "Shiny"), // 5% chance
-        ("Ditto", "Normal", None, "Shiny"),
-        ("Zapdos", "Electric", Some("Flying"), "Legendary"), // 3% chance
-        ("Articuno", "Ice", Some("Flying"), "Legendary"),
-        ("Moltres", "Fire", Some("Flying"), "Legendary"),
-        ("Mewtwo", "Psychic", None, "Legendary"),
-    ];
+
+// Verifica el secreto contra el commit y mezcla el hash del slot del commit
+// (desconocido al momento de commitear) para obtener la semilla del pack
+fn reveal_pack_seed(
+    pack_commit: &Account<PackCommit>,
+    secret: &[u8; 32],
+    slot_hashes: &UncheckedAccount,
+    current_slot: u64,
+) -> Result<u64> {
+    require!(hash(secret).to_bytes() == pack_commit.commitment, ErrorCode::CommitmentMismatch);
+    require!(current_slot > pack_commit.commit_slot, ErrorCode::RevealTooEarly);
+    
+    let slot_hash = find_slot_hash(&slot_hashes.try_borrow_data()?, pack_commit.commit_slot)
+        .ok_or(ErrorCode::CommitExpired)?;
+    
+    let mixed = hashv(&[secret, &slot_hash, pack_commit.user.as_ref()]).to_bytes();
+    let random_seed = u64::from_le_bytes(mixed[..8].try_into().unwrap());
     
-    // Generar números aleatorios basados en el seed
-    let rarity_roll = (seed % 100) as u8;
-    let pokemon_roll = (seed >> 8) % (pokemon_list.len() as u64);
+    // Emitir las entradas de la semilla para auditoría
+    emit!(PackRevealed {
+        user: pack_commit.user,
+        commitment: pack_commit.commitment,
+        secret: *secret,
+        commit_slot: pack_commit.commit_slot,
+        slot_hash,
+        random_seed,
+    });
     
-    // Determinar rareza
-    let rarity = if is_premium {
-        // Pack premium: mejores chances
-        if rarity_roll < 10 { "Legendary" } // 10% legendary
-        else if rarity_roll < 25 { "Shiny" } // 15% shiny
-        else { "Common" } // 75% common
+    Ok(random_seed)
+}
+
+// Busca el hash de un slot en los datos crudos de SlotHashes:
+// u64 cantidad de entradas, luego (u64 slot, [u8; 32] hash) de más nuevo a más viejo
+fn find_slot_hash(data: &[u8], slot: u64) -> Option<[u8; 32]> {
+    let len = u64::from_le_bytes(data.get(..8)?.try_into().ok()?) as usize;
+    
+    for i in 0..len {
+        let entry = data.get(8 + i * 40..8 + (i + 1) * 40)?;
+        let entry_slot = u64::from_le_bytes(entry[..8].try_into().ok()?);
+        
+        if entry_slot == slot {
+            return entry[8..].try_into().ok();
+        }
+        if entry_slot < slot {
+            break;
+        }
+    }
+    
+    None
+}
+
+
+// Mintea 1 token del NFT del Pokémon firmando con la PDA del game_state y
+// revoca la autoridad de mint, así nunca puede existir una segunda copia
+fn mint_pack_pokemon<'info>(
+    game_state: &Account<'info, GameState>,
+    game_state_bump: u8,
+    pokemon_mint: &InterfaceAccount<'info, Mint>,
+    user_pokemon_account: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let seeds = &[b"game_state".as_ref(), &[game_state_bump]];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = MintTo {
+        mint: pokemon_mint.to_account_info(),
+        to: user_pokemon_account.to_account_info(),
+        authority: game_state.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token_interface::mint_to(cpi_ctx, 1)?;
+
+    let cpi_accounts = SetAuthority {
+        current_authority: game_state.to_account_info(),
+        account_or_mint: pokemon_mint.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token_interface::set_authority(cpi_ctx, AuthorityType::MintTokens, None)
+}
+
+// Chances de Legendary en una tirada: weight multiplicado, o solo Legendary
+#[derive(Clone, Copy)]
+enum LegendaryOdds {
+    Boosted(u64),
+    Guaranteed,
+}
+
+// El multiplicador sube de a 1 por cada pack pasado el soft start,
+// y el pack número pity_threshold sin Legendary lo garantiza
+fn legendary_odds(packs_since_legendary: u32, game_state: &GameState) -> LegendaryOdds {
+    if packs_since_legendary + 1 >= game_state.pity_threshold {
+        LegendaryOdds::Guaranteed
+    } else if packs_since_legendary >= game_state.pity_soft_start {
+        LegendaryOdds::Boosted((packs_since_legendary - game_state.pity_soft_start) as u64 + 2)
     } else {
-        // Pack starter: chances normales
-        if rarity_roll < 3 { "Legendary" } // 3% legendary
-        else if rarity_roll < 8 { "Shiny" } // 5% shiny
-        else { "Common" } // 92% common
+        LegendaryOdds::Boosted(1)
+    }
+}
+
+fn generate_pokemon(seed: u64, drop_table: &DropTable, odds: LegendaryOdds) -> Result<Pokemon> {
+    let effective_weight = |entry: &DropEntry, odds: LegendaryOdds| -> u64 {
+        match (odds, entry.rarity) {
+            (LegendaryOdds::Boosted(multiplier), Rarity::Legendary) => entry.weight as u64 * multiplier,
+            (LegendaryOdds::Boosted(_), _) => entry.weight as u64,
+            (LegendaryOdds::Guaranteed, Rarity::Legendary) => entry.weight as u64,
+            (LegendaryOdds::Guaranteed, _) => 0,
+        }
     };
     
-    // Filtrar Pokémon por rareza
-    let filtered_pokemon: Vec<_> = pokemon_list.iter()
-        .enumerate()
-        .filter(|(_, (_, _, _, r))| *r == rarity)
-        .collect();
+    let table_weight = |odds: LegendaryOdds| -> u64 {
+        drop_table.entries.iter().map(|e| effective_weight(e, odds)).sum()
+    };
     
-    if filtered_pokemon.is_empty() {
-        // Fallback a Pokémon común si no hay de la rareza solicitada
-        let (name, type1, type2, _) = pokemon_list[0];
-        return Ok(Pokemon {
-            id: 1,
-            name: name.to_string(),
-            rarity: "Common".to_string(),
-            type1: type1.to_string(),
-            type2: type2.map(|s| s.to_string()),
-        });
-    }
+    // Si la tabla no tiene Legendary no hay nada que garantizar
+    let odds = match odds {
+        LegendaryOdds::Guaranteed if table_weight(odds) == 0 => LegendaryOdds::Boosted(1),
+        _ => odds,
+    };
+    let total_weight = table_weight(odds);
+    require!(total_weight > 0, ErrorCode::EmptyDropTable);
     
-    let selected_index = (pokemon_roll as usize) % filtered_pokemon.len();
-    let (id, (name, type1, type2, _)) = filtered_pokemon[selected_index];
+    // Elegir una entrada ponderada por su weight
+    let mut roll = seed % total_weight;
+    let entry = drop_table.entries.iter()
+        .find(|e| {
+            let weight = effective_weight(e, odds);
+            if roll < weight {
+                true
+            } else {
+                roll -= weight;
+                false
+            }
+        })
+        .ok_or(ErrorCode::EmptyDropTable)?;
     
     Ok(Pokemon {
-        id: (id + 1) as u32,
-        name: name.to_string(),
-        rarity: rarity.to_string(),
-        type1: type1.to_string(),
-        type2: type2.map(|s| s.to_string()),
+        id: entry.species_id,
+        name: entry.name.clone(),
+        rarity: entry.rarity.as_str().to_string(),
+        type1: entry.type1.clone(),
+        type2: entry.type2.clone(),
     })
 }
 
+fn validate_drop_entries(entries: &[DropEntry]) -> Result<()> {
+    require!(!entries.is_empty(), ErrorCode::EmptyDropTable);
+    require!(entries.len() <= MAX_DROP_ENTRIES, ErrorCode::TooManyDropEntries);
+    
+    for entry in entries {
+        require!(entry.weight > 0, ErrorCode::InvalidDropEntry);
+        require!(entry.name.len() <= 20, ErrorCode::InvalidDropEntry);
+        require!(entry.type1.len() <= 10, ErrorCode::InvalidDropEntry);
+        if let Some(type2) = &entry.type2 {
+            require!(type2.len() <= 10, ErrorCode::InvalidDropEntry);
+        }
+    }
+    
+    Ok(())
+}
+
 // ERRORES PERSONALIZADOS
 #[error_code]
 pub enum ErrorCode {
@@ -282,4 +1021,30 @@ pub enum ErrorCode {
     StarterPackAlreadyOpened,
     #[msg("Insufficient funds")]
     InsufficientFunds,
+    #[msg("Secret does not match the pack commitment")]
+    CommitmentMismatch,
+    #[msg("Pack must be revealed in a later slot than the commit")]
+    RevealTooEarly,
+    #[msg("Pack commit expired, its slot hash is no longer available")]
+    CommitExpired,
+    #[msg("Pack commit has not expired yet")]
+    CommitNotExpired,
+    #[msg("Pack commit is for a different pack type")]
+    WrongPackType,
+    #[msg("Only the game admin can do this")]
+    Unauthorized,
+    #[msg("Drop table has no entries with weight")]
+    EmptyDropTable,
+    #[msg("Too many drop table entries")]
+    TooManyDropEntries,
+    #[msg("Invalid drop table entry")]
+    InvalidDropEntry,
+    #[msg("Pity soft start must be below a non-zero threshold")]
+    InvalidPityConfig,
+    #[msg("Invalid number of packs")]
+    InvalidPackCount,
+    #[msg("Too many unclaimed pulls, claim them first")]
+    TooManyPendingPulls,
+    #[msg("No pending pulls to claim")]
+    NoPendingPulls,
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+mod common;
+
+use anchor_spl::token::spl_token;
+use common::{
+    assert_error, claim_pending_pull, commit_premium_packs, create_drop_table, edit_user_state, entry, fetch,
+    initialize_game_and_user, open_premium_packs, pda, start, user_state, warp_slots,
+};
+use pokemon_game::{ErrorCode, PendingPulls, Rarity, MAX_BULK_PACKS};
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn commit_premium_packs_rejects_count_over_max() {
+    let mut context = start().await;
+    initialize_game_and_user(&mut context).await;
+    edit_user_state(&mut context, |state| state.poke_coins = 1_000_000).await;
+
+    let secret = [1u8; 32];
+    assert_error(commit_premium_packs(&mut context, secret, 0).await, ErrorCode::InvalidPackCount);
+    assert_error(
+        commit_premium_packs(&mut context, secret, MAX_BULK_PACKS as u8 + 1).await,
+        ErrorCode::InvalidPackCount,
+    );
+
+    // Nada se cobró por los commits rechazados
+    assert_eq!(user_state(&mut context).await.poke_coins, 1_000_000);
+    commit_premium_packs(&mut context, secret, MAX_BULK_PACKS as u8).await.unwrap();
+    assert_eq!(user_state(&mut context).await.poke_coins, 1_000_000 - 5000 * MAX_BULK_PACKS as u64);
+}
+
+#[tokio::test]
+async fn open_premium_packs_rejects_pending_pulls_over_max() {
+    let mut context = start().await;
+    let user = context.payer.pubkey();
+    initialize_game_and_user(&mut context).await;
+    create_drop_table(&mut context, true, vec![entry(25, "Pikachu", Rarity::Common, 1)]).await.unwrap();
+    edit_user_state(&mut context, |state| state.poke_coins = 1_000_000).await;
+
+    let secret = [1u8; 32];
+    commit_premium_packs(&mut context, secret, MAX_BULK_PACKS as u8).await.unwrap();
+    warp_slots(&mut context, 2).await;
+    open_premium_packs(&mut context, secret).await.unwrap();
+
+    let pending_pulls = pda(&[b"pending_pulls", user.as_ref()]);
+    assert_eq!(fetch::<PendingPulls>(&mut context, pending_pulls).await.pulls.len(), MAX_BULK_PACKS);
+
+    // Con los resultados sin reclamar no entra ni un pack más
+    let secret = [2u8; 32];
+    commit_premium_packs(&mut context, secret, 1).await.unwrap();
+    warp_slots(&mut context, 2).await;
+    assert_error(open_premium_packs(&mut context, secret).await, ErrorCode::TooManyPendingPulls);
+
+    // Reclamar uno libera lugar para el pack pendiente
+    claim_pending_pull(&mut context, spl_token::ID).await.unwrap();
+    open_premium_packs(&mut context, secret).await.unwrap();
+    assert_eq!(fetch::<PendingPulls>(&mut context, pending_pulls).await.pulls.len(), MAX_BULK_PACKS);
+}
@@ -0,0 +1,314 @@
+// Cada archivo de tests usa solo una parte de estos helpers
+#![allow(dead_code)]
+
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::system_program;
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use pokemon_game::{DropEntry, ErrorCode, PokemonNft, Rarity, UserState};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+// El entrypoint de anchor pide cuentas con lifetime 'info
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    pokemon_game::entry(program_id, accounts, data)
+}
+
+pub async fn start() -> ProgramTestContext {
+    let program_test = ProgramTest::new("pokemon_game", pokemon_game::ID, processor!(process_instruction));
+    program_test.start_with_context().await
+}
+
+pub async fn send(context: &mut ProgramTestContext, instruction: Instruction, signers: &[&Keypair]) -> Result<(), String> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await.map_err(|e| e.to_string())
+}
+
+// Anchor devuelve los errores del programa como "custom program error: 0x..."
+pub fn assert_error(result: Result<(), String>, error: ErrorCode) {
+    let code = format!("custom program error: {:#x}", u32::from(error));
+    let message = result.expect_err("the instruction should have failed");
+    assert!(message.contains(&code), "expected {code}, got: {message}");
+}
+
+pub fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &pokemon_game::ID).0
+}
+
+pub fn entry(species_id: u32, name: &str, rarity: Rarity, weight: u16) -> DropEntry {
+    DropEntry {
+        species_id,
+        name: name.to_string(),
+        rarity,
+        type1: "Normal".to_string(),
+        type2: None,
+        weight,
+    }
+}
+
+// El payer del contexto es admin del juego y también el usuario que abre packs
+pub async fn initialize_game_and_user(context: &mut ProgramTestContext) {
+    let user = context.payer.pubkey();
+
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::InitializeGame {
+            game_state: pda(&[b"game_state"]),
+            admin: user,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::InitializeGame {}.data(),
+    }, &[]).await.unwrap();
+
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::InitializeUser {
+            user_state: pda(&[b"user_state", user.as_ref()]),
+            user,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::InitializeUser {}.data(),
+    }, &[]).await.unwrap();
+}
+
+pub async fn create_drop_table(
+    context: &mut ProgramTestContext,
+    is_premium: bool,
+    entries: Vec<DropEntry>,
+) -> Result<(), String> {
+    let admin = context.payer.pubkey();
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::CreateDropTable {
+            drop_table: pda(&[b"drop_table", &[is_premium as u8]]),
+            game_state: pda(&[b"game_state"]),
+            admin,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::CreateDropTable { is_premium, entries }.data(),
+    }, &[]).await
+}
+
+pub async fn update_drop_table(
+    context: &mut ProgramTestContext,
+    is_premium: bool,
+    entries: Vec<DropEntry>,
+) -> Result<(), String> {
+    let admin = context.payer.pubkey();
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::UpdateDropTable {
+            drop_table: pda(&[b"drop_table", &[is_premium as u8]]),
+            game_state: pda(&[b"game_state"]),
+            admin,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::UpdateDropTable { entries }.data(),
+    }, &[]).await
+}
+
+pub async fn set_pity_config(context: &mut ProgramTestContext, pity_threshold: u32, pity_soft_start: u32) {
+    let admin = context.payer.pubkey();
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::SetPityConfig {
+            game_state: pda(&[b"game_state"]),
+            admin,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::SetPityConfig { pity_threshold, pity_soft_start }.data(),
+    }, &[]).await.unwrap();
+}
+
+fn commit_pack_accounts(user: Pubkey) -> Vec<solana_sdk::instruction::AccountMeta> {
+    pokemon_game::accounts::CommitPack {
+        pack_commit: pda(&[b"pack_commit", user.as_ref()]),
+        user_state: pda(&[b"user_state", user.as_ref()]),
+        user,
+        system_program: system_program::ID,
+    }.to_account_metas(None)
+}
+
+pub async fn commit_pack(context: &mut ProgramTestContext, secret: [u8; 32], is_premium: bool) -> Result<(), String> {
+    let user = context.payer.pubkey();
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: commit_pack_accounts(user),
+        data: pokemon_game::instruction::CommitPack {
+            commitment: hash(&secret).to_bytes(),
+            is_premium,
+        }.data(),
+    }, &[]).await
+}
+
+pub async fn commit_premium_packs(context: &mut ProgramTestContext, secret: [u8; 32], count: u8) -> Result<(), String> {
+    let user = context.payer.pubkey();
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: commit_pack_accounts(user),
+        data: pokemon_game::instruction::CommitPremiumPacks {
+            commitment: hash(&secret).to_bytes(),
+            count,
+        }.data(),
+    }, &[]).await
+}
+
+pub async fn close_expired_commit(context: &mut ProgramTestContext) -> Result<(), String> {
+    let user = context.payer.pubkey();
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::CloseExpiredCommit {
+            pack_commit: pda(&[b"pack_commit", user.as_ref()]),
+            user,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::CloseExpiredCommit {}.data(),
+    }, &[]).await
+}
+
+// El reveal tiene que caer en un slot posterior al commit
+pub async fn warp_slots(context: &mut ProgramTestContext, slots: u64) {
+    let slot = context.banks_client.get_root_slot().await.unwrap();
+    context.warp_to_slot(slot + slots).unwrap();
+}
+
+// Devuelve el mint del NFT obtenido
+pub async fn open_starter_pack(
+    context: &mut ProgramTestContext,
+    secret: [u8; 32],
+    token_program: Pubkey,
+) -> Result<Pubkey, String> {
+    let user = context.payer.pubkey();
+    let pokemon_mint = Keypair::new();
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::OpenStarterPack {
+            user_state: pda(&[b"user_state", user.as_ref()]),
+            game_state: pda(&[b"game_state"]),
+            drop_table: pda(&[b"drop_table", &[0]]),
+            pack_commit: pda(&[b"pack_commit", user.as_ref()]),
+            slot_hashes: sysvar::slot_hashes::ID,
+            pokemon_nft: pda(&[b"pokemon", pokemon_mint.pubkey().as_ref()]),
+            pokemon_mint: pokemon_mint.pubkey(),
+            user_pokemon_account:
+                get_associated_token_address_with_program_id(&user, &pokemon_mint.pubkey(), &token_program),
+            user,
+            token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::OpenStarterPack { secret }.data(),
+    }, &[&pokemon_mint]).await?;
+    Ok(pokemon_mint.pubkey())
+}
+
+// Devuelve el mint del NFT obtenido
+pub async fn open_premium_pack(
+    context: &mut ProgramTestContext,
+    secret: [u8; 32],
+    token_program: Pubkey,
+) -> Result<Pubkey, String> {
+    let user = context.payer.pubkey();
+    let pokemon_mint = Keypair::new();
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::OpenPremiumPack {
+            user_state: pda(&[b"user_state", user.as_ref()]),
+            game_state: pda(&[b"game_state"]),
+            drop_table: pda(&[b"drop_table", &[1]]),
+            pack_commit: pda(&[b"pack_commit", user.as_ref()]),
+            slot_hashes: sysvar::slot_hashes::ID,
+            pokemon_nft: pda(&[b"pokemon", pokemon_mint.pubkey().as_ref()]),
+            pokemon_mint: pokemon_mint.pubkey(),
+            user_pokemon_account:
+                get_associated_token_address_with_program_id(&user, &pokemon_mint.pubkey(), &token_program),
+            user,
+            token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::OpenPremiumPack { secret }.data(),
+    }, &[&pokemon_mint]).await?;
+    Ok(pokemon_mint.pubkey())
+}
+
+pub async fn open_premium_packs(context: &mut ProgramTestContext, secret: [u8; 32]) -> Result<(), String> {
+    let user = context.payer.pubkey();
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::OpenPremiumPacks {
+            user_state: pda(&[b"user_state", user.as_ref()]),
+            game_state: pda(&[b"game_state"]),
+            drop_table: pda(&[b"drop_table", &[1]]),
+            pack_commit: pda(&[b"pack_commit", user.as_ref()]),
+            slot_hashes: sysvar::slot_hashes::ID,
+            pending_pulls: pda(&[b"pending_pulls", user.as_ref()]),
+            user,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::OpenPremiumPacks { secret }.data(),
+    }, &[]).await
+}
+
+pub async fn claim_pending_pull(context: &mut ProgramTestContext, token_program: Pubkey) -> Result<Pubkey, String> {
+    let user = context.payer.pubkey();
+    let pokemon_mint = Keypair::new();
+    send(context, Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::ClaimPendingPull {
+            user_state: pda(&[b"user_state", user.as_ref()]),
+            game_state: pda(&[b"game_state"]),
+            pending_pulls: pda(&[b"pending_pulls", user.as_ref()]),
+            pokemon_nft: pda(&[b"pokemon", pokemon_mint.pubkey().as_ref()]),
+            pokemon_mint: pokemon_mint.pubkey(),
+            user_pokemon_account:
+                get_associated_token_address_with_program_id(&user, &pokemon_mint.pubkey(), &token_program),
+            user,
+            token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::ClaimPendingPull {}.data(),
+    }, &[&pokemon_mint]).await?;
+    Ok(pokemon_mint.pubkey())
+}
+
+pub async fn fetch<T: AccountDeserialize>(context: &mut ProgramTestContext, address: Pubkey) -> T {
+    let account = context.banks_client.get_account(address).await.unwrap().unwrap();
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+pub async fn user_state(context: &mut ProgramTestContext) -> UserState {
+    let user = context.payer.pubkey();
+    fetch(context, pda(&[b"user_state", user.as_ref()])).await
+}
+
+pub async fn pokemon_nft(context: &mut ProgramTestContext, pokemon_mint: Pubkey) -> PokemonNft {
+    fetch(context, pda(&[b"pokemon", pokemon_mint.as_ref()])).await
+}
+
+// No hay instrucción para cargar PokéCoins ni para mover el pity, así que se escribe la cuenta a mano
+pub async fn edit_user_state(context: &mut ProgramTestContext, edit: impl FnOnce(&mut UserState)) {
+    let user = context.payer.pubkey();
+    let address = pda(&[b"user_state", user.as_ref()]);
+    let mut account = context.banks_client.get_account(address).await.unwrap().unwrap();
+    let mut state = UserState::try_deserialize(&mut account.data.as_slice()).unwrap();
+    edit(&mut state);
+    let mut data = Vec::with_capacity(account.data.len());
+    state.try_serialize(&mut data).unwrap();
+    account.data[..data.len()].copy_from_slice(&data);
+    context.set_account(&address, &AccountSharedData::from(account));
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{assert_error, create_drop_table, entry, initialize_game_and_user, start, update_drop_table};
+use pokemon_game::{ErrorCode, Rarity, MAX_DROP_ENTRIES};
+
+#[tokio::test]
+async fn create_drop_table_rejects_invalid_weights() {
+    let mut context = start().await;
+    initialize_game_and_user(&mut context).await;
+
+    // Sin entradas la suma de weights es 0
+    assert_error(create_drop_table(&mut context, false, vec![]).await, ErrorCode::EmptyDropTable);
+
+    // Una entrada con weight 0 nunca podría salir
+    let entries = vec![
+        entry(25, "Pikachu", Rarity::Common, 10),
+        entry(150, "Mewtwo", Rarity::Legendary, 0),
+    ];
+    assert_error(create_drop_table(&mut context, false, entries).await, ErrorCode::InvalidDropEntry);
+
+    let entries = (0..=MAX_DROP_ENTRIES as u32).map(|id| entry(id, "Rattata", Rarity::Common, 1)).collect();
+    assert_error(create_drop_table(&mut context, false, entries).await, ErrorCode::TooManyDropEntries);
+
+    create_drop_table(&mut context, false, vec![entry(25, "Pikachu", Rarity::Common, 1)]).await.unwrap();
+}
+
+#[tokio::test]
+async fn update_drop_table_rejects_invalid_weights() {
+    let mut context = start().await;
+    initialize_game_and_user(&mut context).await;
+    create_drop_table(&mut context, true, vec![entry(25, "Pikachu", Rarity::Common, 1)]).await.unwrap();
+
+    assert_error(update_drop_table(&mut context, true, vec![]).await, ErrorCode::EmptyDropTable);
+
+    let entries = vec![entry(150, "Mewtwo", Rarity::Legendary, 0)];
+    assert_error(update_drop_table(&mut context, true, entries).await, ErrorCode::InvalidDropEntry);
+
+    // Una temporada nueva con weights válidos sí se acepta
+    let entries = vec![
+        entry(1, "Bulbasaur", Rarity::Common, 90),
+        entry(151, "Mew", Rarity::Legendary, 10),
+    ];
+    update_drop_table(&mut context, true, entries).await.unwrap();
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use anchor_lang::solana_program::slot_hashes::MAX_ENTRIES;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::error::TokenError;
+use anchor_spl::token_2022::spl_token_2022::extension::StateWithExtensions;
+use common::{
+    assert_error, close_expired_commit, commit_pack, create_drop_table, entry, initialize_game_and_user,
+    open_starter_pack, send, start, warp_slots,
+};
+use pokemon_game::{ErrorCode, Rarity};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn pack_pokemon_mint_cannot_be_minted_twice() {
+    open_starter_pack_and_mint_again(spl_token::ID).await;
+}
+
+#[tokio::test]
+async fn pack_pokemon_mint_cannot_be_minted_twice_token_2022() {
+    open_starter_pack_and_mint_again(spl_token_2022::ID).await;
+}
+
+async fn open_starter_pack_and_mint_again(token_program: Pubkey) {
+    let mut context = start().await;
+    let user = context.payer.pubkey();
+
+    initialize_game_and_user(&mut context).await;
+    create_drop_table(&mut context, false, vec![entry(25, "Pikachu", Rarity::Common, 1)]).await.unwrap();
+
+    // Commit en un slot, revelar en uno posterior
+    let secret = [7u8; 32];
+    commit_pack(&mut context, secret, false).await.unwrap();
+    warp_slots(&mut context, 2).await;
+    let pokemon_mint = open_starter_pack(&mut context, secret, token_program).await.unwrap();
+
+    // El NFT existe y nadie puede mintear otra copia
+    let mint_account = context.banks_client.get_account(pokemon_mint).await.unwrap().unwrap();
+    assert_eq!(mint_account.owner, token_program);
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data).unwrap().base;
+    assert_eq!(mint.supply, 1);
+    assert!(mint.mint_authority.is_none());
+
+    let user_pokemon_account = get_associated_token_address_with_program_id(&user, &pokemon_mint, &token_program);
+    let second_mint = spl_token_2022::instruction::mint_to(
+        &token_program,
+        &pokemon_mint,
+        &user_pokemon_account,
+        &user,
+        &[],
+        1,
+    ).unwrap();
+    // Sin autoridad de mint el token program corta con FixedSupply antes de mirar quién firma
+    // (spl_token y spl_token_2022 comparten los códigos de TokenError)
+    let error = send(&mut context, second_mint, &[]).await.unwrap_err();
+    let fixed_supply = format!("custom program error: {:#x}", TokenError::FixedSupply as u32);
+    assert!(error.contains(&fixed_supply), "expected {fixed_supply}, got: {error}");
+}
+
+#[tokio::test]
+async fn expired_starter_commit_cannot_be_rerolled() {
+    let mut context = start().await;
+    initialize_game_and_user(&mut context).await;
+    create_drop_table(&mut context, false, vec![entry(25, "Pikachu", Rarity::Common, 1)]).await.unwrap();
+
+    // Dejar vencer el commit en vez de revelar un mal resultado
+    commit_pack(&mut context, [7u8; 32], false).await.unwrap();
+    warp_slots(&mut context, MAX_ENTRIES as u64 + 2).await;
+    close_expired_commit(&mut context).await.unwrap();
+
+    assert_error(commit_pack(&mut context, [8u8; 32], false).await, ErrorCode::StarterPackAlreadyOpened);
+}
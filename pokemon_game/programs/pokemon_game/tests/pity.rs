@@ -0,0 +1,61 @@
+mod common;
+
+use anchor_spl::token::spl_token;
+use common::{
+    commit_pack, create_drop_table, edit_user_state, entry, initialize_game_and_user, open_premium_pack,
+    pokemon_nft, set_pity_config, start, user_state, warp_slots,
+};
+use pokemon_game::Rarity;
+
+#[tokio::test]
+async fn legendary_pull_resets_pity_counter() {
+    let mut context = start().await;
+    initialize_game_and_user(&mut context).await;
+    create_drop_table(&mut context, true, vec![entry(150, "Mewtwo", Rarity::Legendary, 1)]).await.unwrap();
+    edit_user_state(&mut context, |state| {
+        state.poke_coins = 5000;
+        state.premium_packs_since_legendary = 12;
+    }).await;
+
+    let secret = [3u8; 32];
+    commit_pack(&mut context, secret, true).await.unwrap();
+    warp_slots(&mut context, 2).await;
+    let pokemon_mint = open_premium_pack(&mut context, secret, spl_token::ID).await.unwrap();
+
+    assert_eq!(pokemon_nft(&mut context, pokemon_mint).await.pokemon.rarity, "Legendary");
+    assert_eq!(user_state(&mut context).await.premium_packs_since_legendary, 0);
+}
+
+#[tokio::test]
+async fn pity_threshold_guarantees_legendary() {
+    let mut context = start().await;
+    initialize_game_and_user(&mut context).await;
+    set_pity_config(&mut context, 5, 4).await;
+
+    // Sin pity el Legendary sale 1 de cada 65536 packs
+    let entries = vec![
+        entry(19, "Rattata", Rarity::Common, u16::MAX),
+        entry(150, "Mewtwo", Rarity::Legendary, 1),
+    ];
+    create_drop_table(&mut context, true, entries).await.unwrap();
+    edit_user_state(&mut context, |state| {
+        state.poke_coins = 10_000;
+        state.premium_packs_since_legendary = 3;
+    }).await;
+
+    // Pack 4 sin Legendary: todavía no está garantizado
+    let secret = [4u8; 32];
+    commit_pack(&mut context, secret, true).await.unwrap();
+    warp_slots(&mut context, 2).await;
+    let pokemon_mint = open_premium_pack(&mut context, secret, spl_token::ID).await.unwrap();
+    assert_eq!(pokemon_nft(&mut context, pokemon_mint).await.pokemon.rarity, "Common");
+    assert_eq!(user_state(&mut context).await.premium_packs_since_legendary, 4);
+
+    // Pack 5 = umbral: Legendary seguro
+    let secret = [5u8; 32];
+    commit_pack(&mut context, secret, true).await.unwrap();
+    warp_slots(&mut context, 2).await;
+    let pokemon_mint = open_premium_pack(&mut context, secret, spl_token::ID).await.unwrap();
+    assert_eq!(pokemon_nft(&mut context, pokemon_mint).await.pokemon.rarity, "Legendary");
+    assert_eq!(user_state(&mut context).await.premium_packs_since_legendary, 0);
+}
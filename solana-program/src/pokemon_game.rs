@@ -1,31 +1,177 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, MintTo, Transfer};
-use anchor_spl::associated_token::AssociatedToken;
-use mpl_token_metadata::instruction::create_metadata_accounts_v3;
-use solana_program::program::invoke;
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::slot_hashes::MAX_ENTRIES;
+use anchor_lang::solana_program::sysvar::slot_hashes;
+use anchor_lang::system_program;
+use std::cmp::Reverse;
+use anchor_spl::associated_token::{self, AssociatedToken};
+use anchor_spl::token_2022_extensions::transfer_fee::{harvest_withheld_tokens_to_mint, HarvestWithheldTokensToMint};
+use anchor_spl::token_interface::spl_token_2022::extension::transfer_fee::TransferFeeAmount;
+use anchor_spl::token_interface::spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use anchor_spl::token_interface::spl_token_2022;
+use anchor_spl::token_interface::spl_token_2022::instruction::AuthorityType;
+use anchor_spl::token_interface::spl_token_metadata_interface::state::Field;
+use anchor_spl::token_interface::{
+    self, Burn, CloseAccount, Mint, MintTo, SetAuthority, TokenAccount, TokenGroupInitialize,
+    TokenInterface, TokenMemberInitialize, TokenMetadataInitialize, TokenMetadataUpdateField,
+    TransferChecked,
+};
+use mpl_bubblegum::hash::{hash_creators, hash_metadata};
+use mpl_bubblegum::instructions::{BurnCpiBuilder, CreateTreeConfigCpiBuilder, MintV1CpiBuilder};
+use mpl_bubblegum::programs::{SPL_ACCOUNT_COMPRESSION_ID, SPL_NOOP_ID};
+use mpl_bubblegum::types::{
+    Creator as LeafCreator, MetadataArgs, TokenProgramVersion, TokenStandard,
+};
 
-declare_id!("PokeGamE1111111111111111111111111111111111111");
+declare_id!("567neeXeNcBSCBh1E64CdWD58rcRGJThL37qAAeQMcmE");
 
+pub const MAX_BOX_SIZE: usize = 100;
+pub const MAX_FEE_BPS: u16 = 1_000; // 10%
+pub const MAX_AUCTION_DURATION: i64 = 7 * 24 * 60 * 60;
+pub const MAX_RENTAL_DURATION: i64 = 30 * 24 * 60 * 60;
+pub const AUCTION_EXTENSION: i64 = 5 * 60; // bids in the last 5 minutes extend the auction
+pub const MAX_TRADE_POKEMON: usize = 6;
+pub const MAX_BASE_URI_LEN: usize = 100;
+pub const MAX_NICKNAME_LEN: usize = 12;
+pub const MAX_LEVEL: u8 = 100;
+pub const STARTER_LEVEL: u8 = 5;
+pub const MAX_ITEM_NAME_LEN: usize = 32;
+pub const MAX_INVENTORY_SLOTS: usize = 32;
+pub const MAX_SPECIES_ABILITIES: usize = 3;
+pub const DEFAULT_BATTLE_ITEM_LIMIT: u8 = 4;
+pub const MAX_STAT_STAGE: i8 = 6;
+pub const WEATHER_TURNS: u8 = 8; // each move or battle item use is a turn
+pub const MAX_SPIKES: u8 = 3;
+pub const SHINY_VALUE_MULTIPLIER: u64 = 5;
+pub const SLOTS_PER_DAY: u64 = 216_000; // ~400ms slots
+pub const BATTLE_REWARD_PER_LEVEL: u64 = 10;
+pub const RANK_BONUS_PERCENT: u64 = 5; // per trainer level of the winner
+pub const MAX_ZONE_SPAWNS: usize = 16;
+pub const MAX_TEAM_SIZE: usize = 6;
+pub const MAX_NPC_NAME_LEN: usize = 32;
+pub const MAX_BADGES: usize = 8;
+pub const BALL_BONUS_BASE: u32 = 10; // Poke Ball `effect` is the catch bonus in tenths
+pub const ENCOUNTER_COOLDOWN: i64 = 10 * 60; // between two `encounter_wild` calls
+pub const BATTLE_TURN_TIMEOUT: i64 = 60 * 60; // a trainer who doesn't act for this long forfeits
+
+// The file is also named pokemon_game, so the #[program] module nests under itself
+#[allow(clippy::module_inception)]
 #[program]
 pub mod pokemon_game {
     use super::*;
 
-    /// Initialize the Pokemon game program
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    /// Initialize the Pokemon game program and its verified NFT collection
+    pub fn initialize(ctx: Context<Initialize>, royalty_bps: u16, base_uri: String) -> Result<()> {
+        require!(royalty_bps <= MAX_FEE_BPS, ErrorCode::InvalidFee);
+        require!(base_uri.len() <= MAX_BASE_URI_LEN, ErrorCode::BaseUriTooLong);
+
         let game_state = &mut ctx.accounts.game_state;
         game_state.authority = ctx.accounts.authority.key();
         game_state.poke_coin_mint = ctx.accounts.poke_coin_mint.key();
         game_state.treasury = ctx.accounts.treasury.key();
+        game_state.collection_mint = ctx.accounts.collection_mint.key();
         game_state.total_battles = 0;
         game_state.total_trainers = 0;
         game_state.total_pokemon = 0;
         game_state.season = 1;
-        game_state.bump = *ctx.bumps.get("game_state").unwrap();
-        
+        game_state.season_emission_cap = 0; // no battle rewards until the authority sets a budget
+        game_state.season_emitted = 0;
+        game_state.battle_item_limit = DEFAULT_BATTLE_ITEM_LIMIT;
+        game_state.marketplace_fee_bps = 250; // 2.5% protocol fee
+        game_state.royalty_bps = royalty_bps;
+        game_state.base_uri = base_uri;
+        game_state.bump = ctx.bumps.game_state;
+
+        let seeds = &[
+            b"game_state".as_ref(),
+            &[game_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Mint the collection NFT
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            to: ctx.accounts.collection_token_account.to_account_info(),
+            authority: ctx.accounts.game_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token_interface::mint_to(cpi_ctx, 1)?;
+
+        // Token-2022 metadata lives in the collection mint itself (metadata pointer)
+        initialize_mint_metadata(
+            &ctx.accounts.collection_mint.to_account_info(),
+            &ctx.accounts.game_state,
+            &ctx.accounts.token_program,
+            "Pokemon Game".to_string(),
+            format!("{}/collection", ctx.accounts.game_state.base_uri),
+        )?;
+
+        // The collection mint is the token group every Pokemon joins as a member
+        let cpi_accounts = TokenGroupInitialize {
+            token_program_id: ctx.accounts.token_program.to_account_info(),
+            group: ctx.accounts.collection_mint.to_account_info(),
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            mint_authority: ctx.accounts.game_state.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+        token_interface::token_group_initialize(cpi_ctx, Some(ctx.accounts.game_state.key()), u32::MAX)?;
+
+        revoke_mint_authority(
+            &ctx.accounts.collection_mint.to_account_info(),
+            &ctx.accounts.game_state,
+            &ctx.accounts.token_program,
+        )?;
+        fund_mint_rent(
+            &ctx.accounts.collection_mint.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program,
+        )?;
+
         msg!("Pokemon Game program initialized!");
         Ok(())
     }
 
+    /// Update the royalty charged on secondary sales of newly caught Pokemon
+    pub fn set_royalty_bps(ctx: Context<UpdateGameConfig>, royalty_bps: u16) -> Result<()> {
+        require!(royalty_bps <= MAX_FEE_BPS, ErrorCode::InvalidFee);
+        ctx.accounts.game_state.royalty_bps = royalty_bps;
+        Ok(())
+    }
+
+    /// Change the host serving Pokemon metadata JSON; existing NFTs pick it up on their next refresh
+    pub fn set_base_uri(ctx: Context<UpdateGameConfig>, base_uri: String) -> Result<()> {
+        require!(base_uri.len() <= MAX_BASE_URI_LEN, ErrorCode::BaseUriTooLong);
+        ctx.accounts.game_state.base_uri = base_uri;
+        Ok(())
+    }
+
+    /// Set how many PokeCoin base units battle rewards may pay out per season
+    pub fn set_season_emission_cap(ctx: Context<UpdateGameConfig>, emission_cap: u64) -> Result<()> {
+        ctx.accounts.game_state.season_emission_cap = emission_cap;
+        Ok(())
+    }
+
+    /// Start the next season with a fresh battle reward budget
+    pub fn start_new_season(ctx: Context<UpdateGameConfig>) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+        game_state.season += 1;
+        game_state.season_emitted = 0;
+
+        emit!(SeasonStarted {
+            season: game_state.season,
+            emission_cap: game_state.season_emission_cap,
+        });
+
+        Ok(())
+    }
+
+    /// Set how many items each side may use per battle, e.g. zero for ranked play
+    pub fn set_battle_item_limit(ctx: Context<UpdateGameConfig>, item_limit: u8) -> Result<()> {
+        ctx.accounts.game_state.battle_item_limit = item_limit;
+        Ok(())
+    }
+
     /// Register a new trainer
     pub fn register_trainer(
         ctx: Context<RegisterTrainer>,
@@ -45,8 +191,9 @@ pub mod pokemon_game {
         trainer.badges = Vec::new();
         trainer.pokemon_team = Vec::new();
         trainer.pokemon_box = Vec::new();
+        trainer.last_encounter_at = 0;
         trainer.created_at = Clock::get()?.unix_timestamp;
-        trainer.bump = *ctx.bumps.get("trainer").unwrap();
+        trainer.bump = ctx.bumps.trainer;
 
         game_state.total_trainers += 1;
 
@@ -64,7 +211,7 @@ pub mod pokemon_game {
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        token::mint_to(cpi_ctx, 1000 * 1_000_000_000)?; // 1000 coins with 9 decimals
+        token_interface::mint_to(cpi_ctx, 1000 * 1_000_000_000)?; // 1000 coins with 9 decimals
 
         emit!(TrainerRegistered {
             trainer: trainer.public_key,
@@ -74,450 +221,5192 @@ pub mod pokemon_game {
         Ok(())
     }
 
-    /// Catch/Mint a new Pokemon NFT
+    /// Mint a Pokemon NFT directly (starters and gifts). Only the game authority can pay for
+    /// a direct mint; everyone else catches Pokemon through wild encounters.
     pub fn catch_pokemon(
         ctx: Context<CatchPokemon>,
         pokemon_data: PokemonData,
     ) -> Result<()> {
-        let trainer = &mut ctx.accounts.trainer;
-        let game_state = &mut ctx.accounts.game_state;
-        
-        // Initialize Pokemon NFT
-        let pokemon = &mut ctx.accounts.pokemon;
-        pokemon.trainer = trainer.public_key;
-        pokemon.species_id = pokemon_data.species_id;
-        pokemon.name = pokemon_data.name.clone();
-        pokemon.level = 5; // All Pokemon start at level 5
-        pokemon.experience = 0;
-        pokemon.hp = calculate_stat(pokemon_data.base_hp, pokemon.level, 31); // Perfect IVs for now
-        pokemon.attack = calculate_stat(pokemon_data.base_attack, pokemon.level, 31);
-        pokemon.defense = calculate_stat(pokemon_data.base_defense, pokemon.level, 31);
-        pokemon.sp_attack = calculate_stat(pokemon_data.base_sp_attack, pokemon.level, 31);
-        pokemon.sp_defense = calculate_stat(pokemon_data.base_sp_defense, pokemon.level, 31);
-        pokemon.speed = calculate_stat(pokemon_data.base_speed, pokemon.level, 31);
-        pokemon.types = pokemon_data.types;
-        pokemon.moves = pokemon_data.moves;
-        pokemon.nature = Nature::Hardy; // Default nature
-        pokemon.is_shiny = false; // TODO: Add shiny probability
-        pokemon.caught_at = Clock::get()?.unix_timestamp;
-        pokemon.mint = ctx.accounts.pokemon_mint.key();
-        pokemon.bump = *ctx.bumps.get("pokemon").unwrap();
-
-        // Mint the NFT
+        require!(
+            ctx.accounts.payer.key() == ctx.accounts.game_state.authority,
+            ErrorCode::DirectCatchNotAllowed
+        );
+        // Gifts are authority-signed, so the ability roll only needs to differ per Pokemon
+        let ability_seed = hashv(&[
+            ctx.accounts.trainer_authority.key().as_ref(),
+            &ctx.accounts.game_state.total_pokemon.to_le_bytes(),
+        ])
+        .to_bytes();
+        create_pokemon(ctx.accounts, ctx.bumps.pokemon, pokemon_data, STARTER_LEVEL, ability_seed)
+    }
+
+    /// Create the game's concurrent Merkle tree for compressed Pokemon. The tree account
+    /// itself is allocated by the client beforehand; the game state becomes its creator.
+    pub fn create_pokemon_tree(
+        ctx: Context<CreatePokemonTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        let game_state = &ctx.accounts.game_state;
         let seeds = &[
             b"game_state".as_ref(),
             &[game_state.bump],
         ];
-        let signer = &[&seeds[..]];
 
-        let cpi_accounts = MintTo {
-            mint: ctx.accounts.pokemon_mint.to_account_info(),
-            to: ctx.accounts.trainer_pokemon_account.to_account_info(),
-            authority: ctx.accounts.game_state.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        token::mint_to(cpi_ctx, 1)?;
-
-        // Create metadata for the Pokemon NFT
-        let metadata_instruction = create_metadata_accounts_v3(
-            ctx.accounts.token_metadata_program.key(),
-            ctx.accounts.metadata_account.key(),
-            ctx.accounts.pokemon_mint.key(),
-            ctx.accounts.game_state.key(),
-            ctx.accounts.payer.key(),
-            ctx.accounts.game_state.key(),
-            format!("{} #{}", pokemon_data.name, pokemon_data.species_id),
-            "PKMN".to_string(),
-            format!("https://pokemon-api.com/metadata/{}", ctx.accounts.pokemon_mint.key()),
-            Some(vec![]), 
-            0,
-            true,
-            false,
-            None,
-            None,
-            None,
-        );
+        CreateTreeConfigCpiBuilder::new(&ctx.accounts.bubblegum_program)
+            .tree_config(&ctx.accounts.tree_config)
+            .merkle_tree(&ctx.accounts.merkle_tree)
+            .payer(&ctx.accounts.authority.to_account_info())
+            .tree_creator(&game_state.to_account_info())
+            .log_wrapper(&ctx.accounts.log_wrapper)
+            .compression_program(&ctx.accounts.compression_program)
+            .system_program(&ctx.accounts.system_program.to_account_info())
+            .max_depth(max_depth)
+            .max_buffer_size(max_buffer_size)
+            .public(false)
+            .invoke_signed(&[&seeds[..]])?;
 
-        invoke(
-            &metadata_instruction,
-            &[
-                ctx.accounts.metadata_account.to_account_info(),
-                ctx.accounts.pokemon_mint.to_account_info(),
-                ctx.accounts.game_state.to_account_info(),
-                ctx.accounts.payer.to_account_info(),
-                ctx.accounts.game_state.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-                ctx.accounts.rent.to_account_info(),
-                ctx.accounts.token_metadata_program.to_account_info(),
-            ],
-        )?;
+        ctx.accounts.game_state.pokemon_tree = ctx.accounts.merkle_tree.key();
 
-        // Add to trainer's collection
-        if trainer.pokemon_team.len() < 6 {
-            trainer.pokemon_team.push(ctx.accounts.pokemon_mint.key());
-        } else {
-            trainer.pokemon_box.push(ctx.accounts.pokemon_mint.key());
-        }
+        Ok(())
+    }
+
+    /// Mint a compressed Pokemon into the game tree, e.g. for free starter packs. The species
+    /// data is committed to in the leaf so it can later be decompressed into a full Pokemon.
+    pub fn mint_compressed_pokemon(
+        ctx: Context<MintCompressedPokemon>,
+        pokemon_data: PokemonData,
+    ) -> Result<()> {
+        let game_state = &ctx.accounts.game_state;
+        let seeds = &[
+            b"game_state".as_ref(),
+            &[game_state.bump],
+        ];
 
-        trainer.pokemon_caught += 1;
-        game_state.total_pokemon += 1;
+        let config = CompressedMintConfig {
+            base_uri: game_state.base_uri.clone(),
+            royalty_bps: game_state.royalty_bps,
+            royalty_recipient: game_state.authority,
+        };
+        MintV1CpiBuilder::new(&ctx.accounts.bubblegum_program)
+            .tree_config(&ctx.accounts.tree_config)
+            .leaf_owner(&ctx.accounts.leaf_owner)
+            .leaf_delegate(&ctx.accounts.leaf_owner)
+            .merkle_tree(&ctx.accounts.merkle_tree)
+            .payer(&ctx.accounts.authority.to_account_info())
+            .tree_creator_or_delegate(&game_state.to_account_info())
+            .log_wrapper(&ctx.accounts.log_wrapper)
+            .compression_program(&ctx.accounts.compression_program)
+            .system_program(&ctx.accounts.system_program.to_account_info())
+            .metadata(compressed_pokemon_metadata(&pokemon_data, &config))
+            .invoke_signed(&[&seeds[..]])?;
 
-        emit!(PokemonCaught {
-            trainer: trainer.public_key,
-            pokemon_mint: ctx.accounts.pokemon_mint.key(),
+        emit!(CompressedPokemonMinted {
+            leaf_owner: ctx.accounts.leaf_owner.key(),
+            merkle_tree: ctx.accounts.merkle_tree.key(),
             species_id: pokemon_data.species_id,
-            name: pokemon_data.name,
+            config,
         });
 
         Ok(())
     }
 
-    /// Challenge another trainer to a battle
-    pub fn challenge_trainer(
-        ctx: Context<ChallengeBattle>,
-        wager: u64,
+    /// Burn a compressed Pokemon and mint it as a full Pokemon account and Token-2022 NFT.
+    /// The leaf proof is passed as remaining accounts.
+    pub fn decompress_pokemon<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DecompressPokemon<'info>>,
+        pokemon_data: PokemonData,
+        config: CompressedMintConfig,
+        root: [u8; 32],
+        nonce: u64,
+        index: u32,
     ) -> Result<()> {
-        let battle = &mut ctx.accounts.battle;
-        let challenger = &mut ctx.accounts.challenger;
-        let game_state = &mut ctx.accounts.game_state;
+        let metadata = compressed_pokemon_metadata(&pokemon_data, &config);
+        let data_hash = hash_metadata(&metadata).map_err(|_| ErrorCode::InvalidCompressedPokemon)?;
+        let creator_hash = hash_creators(&metadata.creators);
 
-        battle.id = game_state.total_battles;
-        battle.challenger = challenger.public_key;
-        battle.opponent = ctx.accounts.opponent.key();
-        battle.wager = wager;
-        battle.state = BattleState::Challenged;
-        battle.current_turn = 0;
-        battle.challenger_active_pokemon = None;
-        battle.opponent_active_pokemon = None;
-        battle.winner = None;
-        battle.created_at = Clock::get()?.unix_timestamp;
-        battle.bump = *ctx.bumps.get("battle").unwrap();
+        let trainer_authority = ctx.accounts.catch.trainer_authority.to_account_info();
+        let proof: Vec<(&AccountInfo<'info>, bool, bool)> = ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| (account, false, false))
+            .collect();
+        BurnCpiBuilder::new(&ctx.accounts.bubblegum_program)
+            .tree_config(&ctx.accounts.tree_config)
+            .leaf_owner(&trainer_authority, true)
+            .leaf_delegate(&trainer_authority, false)
+            .merkle_tree(&ctx.accounts.merkle_tree)
+            .log_wrapper(&ctx.accounts.log_wrapper)
+            .compression_program(&ctx.accounts.compression_program)
+            .system_program(&ctx.accounts.catch.system_program.to_account_info())
+            .root(root)
+            .data_hash(data_hash)
+            .creator_hash(creator_hash)
+            .nonce(nonce)
+            .index(index)
+            .add_remaining_accounts(&proof)
+            .invoke()?;
 
-        game_state.total_battles += 1;
+        emit!(PokemonDecompressed {
+            trainer: ctx.accounts.catch.trainer.public_key,
+            pokemon_mint: ctx.accounts.catch.pokemon_mint.key(),
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            nonce,
+        });
 
-        emit!(BattleChallenge {
-            battle_id: battle.id,
-            challenger: battle.challenger,
-            opponent: battle.opponent,
-            wager,
+        // The leaf is fixed when the authority mints it, so its hash can't be ground
+        let ability_seed = hashv(&[
+            ctx.accounts.merkle_tree.key().as_ref(),
+            &nonce.to_le_bytes(),
+            &data_hash,
+        ])
+        .to_bytes();
+        create_pokemon(
+            &mut ctx.accounts.catch,
+            ctx.bumps.catch.pokemon,
+            pokemon_data,
+            STARTER_LEVEL,
+            ability_seed,
+        )
+    }
+
+    /// Apply every level the Pokemon has enough experience for and refresh its metadata
+    pub fn level_up(ctx: Context<UpdatePokemon>) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(pokemon.trainer == ctx.accounts.trainer_authority.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+
+        let previous_level = pokemon.level;
+        while pokemon.level < MAX_LEVEL && pokemon.experience >= experience_for_level(pokemon.level + 1) {
+            pokemon.level += 1;
+        }
+        require!(pokemon.level > previous_level, ErrorCode::NotEnoughExperience);
+        recalculate_stats(pokemon);
+
+        refresh_pokemon_metadata(
+            pokemon,
+            &ctx.accounts.game_state,
+            &ctx.accounts.pokemon_mint.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+        )?;
+
+        emit!(PokemonUpdated {
+            pokemon_mint: pokemon.mint,
+            species_id: pokemon.species_id,
+            level: pokemon.level,
+            nickname: pokemon.nickname.clone(),
+            is_shiny: pokemon.is_shiny,
         });
 
         Ok(())
     }
 
-    /// Accept a battle challenge
-    pub fn accept_battle(ctx: Context<AcceptBattle>) -> Result<()> {
-        let battle = &mut ctx.accounts.battle;
-        
-        require!(battle.state == BattleState::Challenged, ErrorCode::BattleNotChallenged);
-        require!(battle.opponent == ctx.accounts.opponent.key(), ErrorCode::UnauthorizedOpponent);
+    /// Evolve a Pokemon into another species; the game authority vouches for the evolution data
+    pub fn evolve_pokemon(ctx: Context<EvolvePokemon>, evolution: PokemonData) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(pokemon.trainer == ctx.accounts.trainer_authority.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
 
-        battle.state = BattleState::Active;
-        battle.started_at = Some(Clock::get()?.unix_timestamp);
+        apply_evolution(pokemon, &evolution);
 
-        emit!(BattleAccepted {
-            battle_id: battle.id,
-            started_at: battle.started_at.unwrap(),
+        refresh_pokemon_metadata(
+            pokemon,
+            &ctx.accounts.game_state,
+            &ctx.accounts.pokemon_mint.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+        )?;
+
+        emit!(PokemonUpdated {
+            pokemon_mint: pokemon.mint,
+            species_id: pokemon.species_id,
+            level: pokemon.level,
+            nickname: pokemon.nickname.clone(),
+            is_shiny: pokemon.is_shiny,
         });
 
         Ok(())
     }
 
-    /// Select Pokemon for battle
-    pub fn select_pokemon(
-        ctx: Context<SelectPokemon>,
-        pokemon_mint: Pubkey,
-    ) -> Result<()> {
-        let battle = &mut ctx.accounts.battle;
-        let trainer = &ctx.accounts.trainer;
-        let pokemon = &ctx.accounts.pokemon;
+    /// Evolve a Pokemon that was received in a trade into its species' trade evolution
+    pub fn trade_evolve(ctx: Context<TradeEvolve>) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(pokemon.trainer == ctx.accounts.trainer_authority.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+        require!(pokemon.traded, ErrorCode::NoTradeEvolution);
+        require!(
+            ctx.accounts.species.trade_evolution == Some(ctx.accounts.evolution.species_id),
+            ErrorCode::NoTradeEvolution
+        );
 
-        require!(battle.state == BattleState::Active, ErrorCode::BattleNotActive);
-        require!(pokemon.trainer == trainer.public_key, ErrorCode::PokemonNotOwned);
-        require!(pokemon.mint == pokemon_mint, ErrorCode::InvalidPokemonMint);
+        apply_evolution(pokemon, &ctx.accounts.evolution.data);
+        pokemon.traded = false;
 
-        if battle.challenger == trainer.public_key {
-            battle.challenger_active_pokemon = Some(pokemon_mint);
-        } else if battle.opponent == trainer.public_key {
-            battle.opponent_active_pokemon = Some(pokemon_mint);
-        } else {
-            return Err(ErrorCode::TrainerNotInBattle.into());
-        }
+        refresh_pokemon_metadata(
+            pokemon,
+            &ctx.accounts.game_state,
+            &ctx.accounts.pokemon_mint.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+        )?;
 
-        emit!(PokemonSelected {
-            battle_id: battle.id,
-            trainer: trainer.public_key,
-            pokemon_mint,
+        emit!(PokemonUpdated {
+            pokemon_mint: pokemon.mint,
+            species_id: pokemon.species_id,
+            level: pokemon.level,
+            nickname: pokemon.nickname.clone(),
+            is_shiny: pokemon.is_shiny,
         });
 
         Ok(())
     }
 
-    /// Execute a move in battle
-    pub fn use_move(
-        ctx: Context<UseMove>,
-        move_id: u16,
-    ) -> Result<()> {
-        let battle = &mut ctx.accounts.battle;
-        let attacker_pokemon = &mut ctx.accounts.attacker_pokemon;
-        let defender_pokemon = &mut ctx.accounts.defender_pokemon;
-
-        require!(battle.state == BattleState::Active, ErrorCode::BattleNotActive);
-        
-        // Verify it's the correct trainer's turn
-        let is_challenger_turn = battle.current_turn % 2 == 0;
-        let attacker_key = ctx.accounts.trainer.key();
-        
-        if is_challenger_turn {
-            require!(battle.challenger == attacker_key, ErrorCode::NotYourTurn);
-            require!(Some(attacker_pokemon.mint) == battle.challenger_active_pokemon, ErrorCode::InvalidActivePokemon);
-        } else {
-            require!(battle.opponent == attacker_key, ErrorCode::NotYourTurn);
-            require!(Some(attacker_pokemon.mint) == battle.opponent_active_pokemon, ErrorCode::InvalidActivePokemon);
+    /// Give a Pokemon a nickname, or clear it with `None`
+    pub fn set_nickname(ctx: Context<UpdatePokemon>, nickname: Option<String>) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(pokemon.trainer == ctx.accounts.trainer_authority.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+        if let Some(nickname) = &nickname {
+            require!(
+                !nickname.is_empty() && nickname.len() <= MAX_NICKNAME_LEN,
+                ErrorCode::InvalidNickname
+            );
         }
 
-        // Calculate damage (simplified)
-        let damage = calculate_damage(
-            attacker_pokemon, 
-            defender_pokemon, 
-            move_id
-        );
+        pokemon.nickname = nickname;
 
-        defender_pokemon.hp = defender_pokemon.hp.saturating_sub(damage);
+        refresh_pokemon_metadata(
+            pokemon,
+            &ctx.accounts.game_state,
+            &ctx.accounts.pokemon_mint.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+        )?;
 
-        battle.current_turn += 1;
+        emit!(PokemonUpdated {
+            pokemon_mint: pokemon.mint,
+            species_id: pokemon.species_id,
+            level: pokemon.level,
+            nickname: pokemon.nickname.clone(),
+            is_shiny: pokemon.is_shiny,
+        });
 
-        emit!(MoveUsed {
-            battle_id: battle.id,
-            trainer: attacker_key,
-            pokemon_mint: attacker_pokemon.mint,
-            move_id,
-            damage,
+        Ok(())
+    }
+
+    /// Make a Pokemon shiny (events, rewards); only the game authority can grant it
+    pub fn make_shiny(ctx: Context<MakeShiny>) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        pokemon.is_shiny = true;
+
+        refresh_pokemon_metadata(
+            pokemon,
+            &ctx.accounts.game_state,
+            &ctx.accounts.pokemon_mint.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+        )?;
+
+        emit!(PokemonUpdated {
+            pokemon_mint: pokemon.mint,
+            species_id: pokemon.species_id,
+            level: pokemon.level,
+            nickname: pokemon.nickname.clone(),
+            is_shiny: pokemon.is_shiny,
         });
 
-        // Check if Pokemon fainted
-        if defender_pokemon.hp == 0 {
-            // Simplified: Battle ends when one Pokemon faints
-            end_battle(battle, if is_challenger_turn { 0 } else { 1 })?;
+        Ok(())
+    }
+
+    /// Rewrite a Pokemon's metadata from its on-chain state, e.g. after the base URI changed
+    pub fn refresh_metadata(ctx: Context<RefreshMetadata>) -> Result<()> {
+        refresh_pokemon_metadata(
+            &ctx.accounts.pokemon,
+            &ctx.accounts.game_state,
+            &ctx.accounts.pokemon_mint.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    /// Release a Pokemon: burn its NFT, return the rent of its accounts to the owner and pay
+    /// a PokeCoin reward from the treasury scaled by the Pokemon's rarity and level
+    pub fn release_pokemon(ctx: Context<ReleasePokemon>) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(pokemon.trainer == ctx.accounts.owner.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+        check_not_in_battle(pokemon, ctx.accounts.battle.as_ref())?;
+
+        let game_state = &ctx.accounts.game_state;
+        let seeds = &[
+            b"game_state".as_ref(),
+            &[game_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Burn the NFT and close the owner's token account
+        let cpi_accounts = Burn {
+            mint: ctx.accounts.pokemon_mint.to_account_info(),
+            from: ctx.accounts.owner_pokemon_account.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token_interface::burn(CpiContext::new(cpi_program, cpi_accounts), 1)?;
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.owner_pokemon_account.to_account_info(),
+            destination: ctx.accounts.owner.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token_interface::close_account(CpiContext::new(cpi_program, cpi_accounts))?;
+
+        // With the supply back at zero the mint, and the metadata stored in it, can be closed
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.pokemon_mint.to_account_info(),
+            destination: ctx.accounts.owner.to_account_info(),
+            authority: game_state.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token_interface::close_account(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer))?;
+
+        let reward = pokemon_value(pokemon)
+            .checked_mul(10u64.pow(ctx.accounts.poke_coin_mint.decimals as u32))
+            .ok_or(ErrorCode::MathOverflow)?;
+        if reward > 0 {
+            pda_transfer(
+                &ctx.accounts.treasury_coin_account,
+                &ctx.accounts.owner_coin_account,
+                &ctx.accounts.poke_coin_mint,
+                &game_state.to_account_info(),
+                signer,
+                reward,
+                &ctx.accounts.coin_token_program,
+            )?;
         }
 
+        remove_from_collection(&mut ctx.accounts.trainer, &pokemon.mint);
+
+        emit!(PokemonReleased {
+            trainer: pokemon.trainer,
+            pokemon_mint: pokemon.mint,
+            species_id: pokemon.species_id,
+            reward,
+        });
+
         Ok(())
     }
 
-    /// End a battle and distribute rewards
-    pub fn end_battle_and_reward(ctx: Context<EndBattle>) -> Result<()> {
-        let battle = &mut ctx.accounts.battle;
-        let winner_trainer = &mut ctx.accounts.winner_trainer;
-        let loser_trainer = &mut ctx.accounts.loser_trainer;
+    /// Lock a Pokemon in a program vault to earn PokeCoin every slot until it is unstaked
+    pub fn stake_pokemon(ctx: Context<StakePokemon>) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(pokemon.trainer == ctx.accounts.owner.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+        check_not_in_battle(pokemon, ctx.accounts.battle.as_ref())?;
 
-        require!(battle.state == BattleState::Finished, ErrorCode::BattleNotFinished);
+        let clock = Clock::get()?;
+        let stake = &mut ctx.accounts.stake;
+        stake.owner = ctx.accounts.owner.key();
+        stake.pokemon_mint = ctx.accounts.pokemon_mint.key();
+        stake.last_claim_slot = clock.slot;
+        stake.staked_at = clock.unix_timestamp;
+        stake.bump = ctx.bumps.stake;
 
-        // Transfer wager from loser to winner
-        if battle.wager > 0 {
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.loser_coin_account.to_account_info(),
-                to: ctx.accounts.winner_coin_account.to_account_info(),
-                authority: ctx.accounts.loser_trainer.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-            token::transfer(cpi_ctx, battle.wager)?;
-        }
+        pokemon.status = PokemonStatus::Staked;
 
-        // Update trainer stats
-        winner_trainer.battles_won += 1;
-        winner_trainer.experience += 100;
-        loser_trainer.battles_lost += 1;
+        // Move the NFT into the stake vault
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.owner_pokemon_account.to_account_info(),
+            mint: ctx.accounts.pokemon_mint.to_account_info(),
+            to: ctx.accounts.stake_vault.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token_interface::transfer_checked(CpiContext::new(cpi_program, cpi_accounts), 1, 0)?;
 
-        emit!(BattleRewardsDistributed {
-            battle_id: battle.id,
-            winner: winner_trainer.public_key,
-            loser: loser_trainer.public_key,
-            wager: battle.wager,
+        emit!(PokemonStaked {
+            owner: stake.owner,
+            pokemon_mint: stake.pokemon_mint,
+            value_per_day: pokemon_value(pokemon),
         });
 
         Ok(())
     }
-}
 
-// Helper functions
-fn calculate_stat(base_stat: u16, level: u8, iv: u8) -> u16 {
-    ((2 * base_stat as u32 + iv as u32) * level as u32 / 100 + 5) as u16
-}
+    /// Claim the PokeCoin a staked Pokemon has earned so far; it stays staked
+    pub fn claim_staking_rewards(ctx: Context<ClaimStakingRewards>) -> Result<()> {
+        pay_staking_rewards(
+            &mut ctx.accounts.stake,
+            &ctx.accounts.pokemon,
+            &ctx.accounts.game_state,
+            &ctx.accounts.treasury_coin_account,
+            &ctx.accounts.owner_coin_account,
+            &ctx.accounts.poke_coin_mint,
+            &ctx.accounts.coin_token_program,
+        )
+    }
 
-fn calculate_damage(attacker: &Pokemon, defender: &Pokemon, _move_id: u16) -> u16 {
-    // Simplified damage calculation
-    let attack_stat = attacker.attack as u32;
-    let defense_stat = defender.defense as u32;
-    let level = attacker.level as u32;
-    
-    ((((2 * level + 10) / 250) * (attack_stat / defense_stat) * 50 + 2) * 100 / 100) as u16
-}
+    /// Pay out pending staking rewards and return the Pokemon to its owner
+    pub fn unstake_pokemon(ctx: Context<UnstakePokemon>) -> Result<()> {
+        pay_staking_rewards(
+            &mut ctx.accounts.stake,
+            &ctx.accounts.pokemon,
+            &ctx.accounts.game_state,
+            &ctx.accounts.treasury_coin_account,
+            &ctx.accounts.owner_coin_account,
+            &ctx.accounts.poke_coin_mint,
+            &ctx.accounts.coin_token_program,
+        )?;
 
-fn end_battle(battle: &mut Battle, winner: u8) -> Result<()> {
-    battle.state = BattleState::Finished;
-    battle.winner = Some(winner);
-    battle.ended_at = Some(Clock::get()?.unix_timestamp);
+        let stake = &ctx.accounts.stake;
+        let seeds = &[
+            b"stake".as_ref(),
+            stake.pokemon_mint.as_ref(),
+            &[stake.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let authority = stake.to_account_info();
+        let owner = ctx.accounts.owner.to_account_info();
 
-    emit!(BattleEnded {
-        battle_id: battle.id,
-        winner,
-    });
+        pda_transfer(
+            &ctx.accounts.stake_vault,
+            &ctx.accounts.owner_pokemon_account,
+            &ctx.accounts.pokemon_mint,
+            &authority,
+            signer,
+            1,
+            &ctx.accounts.token_program,
+        )?;
+        pda_close(&ctx.accounts.stake_vault, &owner, &authority, signer, &ctx.accounts.token_program)?;
 
-    Ok(())
-}
+        ctx.accounts.pokemon.status = PokemonStatus::Active;
 
-// Account structures and data types follow...
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
+        emit!(PokemonUnstaked {
+            owner: stake.owner,
+            pokemon_mint: stake.pokemon_mint,
+        });
+
+        Ok(())
+    }
+
+    /// Offer a Pokemon for rent: it moves into escrow and any trainer can borrow it for
+    /// battles for `duration` seconds by paying `fee` PokeCoin
+    pub fn offer_rental(ctx: Context<OfferRental>, fee: u64, duration: i64) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(duration > 0 && duration <= MAX_RENTAL_DURATION, ErrorCode::InvalidRentalDuration);
+        require!(pokemon.trainer == ctx.accounts.owner.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+        check_not_in_battle(pokemon, ctx.accounts.battle.as_ref())?;
+
+        let rental = &mut ctx.accounts.rental;
+        rental.owner = ctx.accounts.owner.key();
+        rental.borrower = None;
+        rental.pokemon_mint = ctx.accounts.pokemon_mint.key();
+        rental.fee = fee;
+        rental.duration = duration;
+        rental.expires_at = 0;
+        rental.created_at = Clock::get()?.unix_timestamp;
+        rental.bump = ctx.bumps.rental;
+
+        pokemon.status = PokemonStatus::Lent;
+
+        // Move the NFT into escrow
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.owner_pokemon_account.to_account_info(),
+            mint: ctx.accounts.pokemon_mint.to_account_info(),
+            to: ctx.accounts.escrow_pokemon_account.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token_interface::transfer_checked(CpiContext::new(cpi_program, cpi_accounts), 1, 0)?;
+
+        emit!(RentalOffered {
+            owner: rental.owner,
+            pokemon_mint: rental.pokemon_mint,
+            fee,
+            duration,
+        });
+
+        Ok(())
+    }
+
+    /// Rent an offered Pokemon, paying the owner (minus the protocol fee) up front
+    pub fn rent_pokemon(ctx: Context<RentPokemon>) -> Result<()> {
+        let rental = &mut ctx.accounts.rental;
+        require!(rental.borrower.is_none(), ErrorCode::AlreadyRented);
+        require!(ctx.accounts.borrower.key() != rental.owner, ErrorCode::CannotRentOwnPokemon);
+
+        let fee = protocol_fee(rental.fee, ctx.accounts.game_state.marketplace_fee_bps);
+
+        // Pay the owner and the treasury
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let decimals = ctx.accounts.poke_coin_mint.decimals;
+        if rental.fee > fee {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.borrower_coin_account.to_account_info(),
+                mint: ctx.accounts.poke_coin_mint.to_account_info(),
+                to: ctx.accounts.owner_coin_account.to_account_info(),
+                authority: ctx.accounts.borrower.to_account_info(),
+            };
+            token_interface::transfer_checked(CpiContext::new(cpi_program.clone(), cpi_accounts), rental.fee - fee, decimals)?;
+        }
+
+        if fee > 0 {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.borrower_coin_account.to_account_info(),
+                mint: ctx.accounts.poke_coin_mint.to_account_info(),
+                to: ctx.accounts.treasury_coin_account.to_account_info(),
+                authority: ctx.accounts.borrower.to_account_info(),
+            };
+            token_interface::transfer_checked(CpiContext::new(cpi_program, cpi_accounts), fee, decimals)?;
+        }
+
+        rental.borrower = Some(ctx.accounts.borrower.key());
+        rental.expires_at = Clock::get()?.unix_timestamp + rental.duration;
+
+        emit!(PokemonRented {
+            owner: rental.owner,
+            borrower: ctx.accounts.borrower.key(),
+            pokemon_mint: rental.pokemon_mint,
+            fee: rental.fee,
+            expires_at: rental.expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Take a Pokemon back from escrow: any time while nobody has rented it, otherwise once
+    /// the rental has expired
+    pub fn end_rental(ctx: Context<EndRental>) -> Result<()> {
+        let rental = &ctx.accounts.rental;
+        require!(
+            rental.borrower.is_none() || Clock::get()?.unix_timestamp >= rental.expires_at,
+            ErrorCode::RentalNotExpired
+        );
+
+        let seeds = &[
+            b"rental".as_ref(),
+            rental.pokemon_mint.as_ref(),
+            &[rental.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let authority = rental.to_account_info();
+        let owner = ctx.accounts.owner.to_account_info();
+
+        pda_transfer(
+            &ctx.accounts.escrow_pokemon_account,
+            &ctx.accounts.owner_pokemon_account,
+            &ctx.accounts.pokemon_mint,
+            &authority,
+            signer,
+            1,
+            &ctx.accounts.token_program,
+        )?;
+        pda_close(&ctx.accounts.escrow_pokemon_account, &owner, &authority, signer, &ctx.accounts.token_program)?;
+
+        ctx.accounts.pokemon.status = PokemonStatus::Active;
+
+        emit!(RentalEnded {
+            owner: rental.owner,
+            borrower: rental.borrower,
+            pokemon_mint: rental.pokemon_mint,
+        });
+
+        Ok(())
+    }
+
+    /// Register a species' base data, catch rate, trade evolution and the abilities it can
+    /// have; each caught Pokemon rolls one of them
+    pub fn register_species(
+        ctx: Context<RegisterSpecies>,
+        data: PokemonData,
+        catch_rate: u8,
+        abilities: Vec<Ability>,
+        trade_evolution: Option<u16>,
+    ) -> Result<()> {
+        require!(
+            !abilities.is_empty() && abilities.len() <= MAX_SPECIES_ABILITIES,
+            ErrorCode::InvalidAbilities
+        );
+        require!(catch_rate > 0, ErrorCode::InvalidCatchRate);
+
+        let species = &mut ctx.accounts.species;
+        species.species_id = data.species_id;
+        species.data = data;
+        species.catch_rate = catch_rate;
+        species.trade_evolution = trade_evolution;
+        species.abilities = abilities;
+        species.bump = ctx.bumps.species;
+
+        Ok(())
+    }
+
+    /// Register a move's type, power and field effect
+    pub fn register_move(
+        ctx: Context<RegisterMove>,
+        move_id: u16,
+        move_type: PokemonType,
+        power: u16,
+        effect: Option<MoveEffect>,
+    ) -> Result<()> {
+        let move_def = &mut ctx.accounts.move_def;
+        move_def.move_id = move_id;
+        move_def.move_type = move_type;
+        move_def.power = power;
+        move_def.effect = effect;
+        move_def.bump = ctx.bumps.move_def;
+
+        Ok(())
+    }
+
+    /// Define a zone's spawn table: each entry's chance is its weight over the zone total
+    pub fn create_zone(
+        ctx: Context<CreateZone>,
+        zone_id: u16,
+        spawns: Vec<Spawn>,
+    ) -> Result<()> {
+        require!(
+            !spawns.is_empty() && spawns.len() <= MAX_ZONE_SPAWNS,
+            ErrorCode::InvalidSpawnTable
+        );
+        for spawn in &spawns {
+            require!(
+                spawn.weight > 0
+                    && spawn.min_level > 0
+                    && spawn.min_level <= spawn.max_level
+                    && spawn.max_level <= MAX_LEVEL,
+                ErrorCode::InvalidSpawnTable
+            );
+        }
+
+        let zone = &mut ctx.accounts.zone;
+        zone.zone_id = zone_id;
+        zone.spawns = spawns;
+        zone.bump = ctx.bumps.zone;
+
+        Ok(())
+    }
+
+    /// Walk into the grass: commit to `hash(secret)` now, roll the encounter with
+    /// `reveal_encounter` in a later slot. Each encounter starts a cooldown, so fleeing an
+    /// unwanted roll doesn't buy an immediate reroll.
+    pub fn encounter_wild(
+        ctx: Context<EncounterWild>,
+        zone_id: u16,
+        commitment: [u8; 32],
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let trainer = &mut ctx.accounts.trainer;
+        require!(
+            now >= trainer.last_encounter_at + ENCOUNTER_COOLDOWN,
+            ErrorCode::EncounterCooldown
+        );
+        trainer.last_encounter_at = now;
+
+        let encounter = &mut ctx.accounts.encounter;
+        encounter.trainer = ctx.accounts.owner.key();
+        encounter.zone_id = zone_id;
+        encounter.commitment = commitment;
+        encounter.commit_slot = Clock::get()?.slot;
+        encounter.seed = [0; 32];
+        encounter.species_id = None;
+        encounter.level = 0;
+        encounter.hp = 0;
+        encounter.catch_attempts = 0;
+        encounter.pending_throw = None;
+        encounter.caught = false;
+        encounter.bump = ctx.bumps.encounter;
+
+        Ok(())
+    }
+
+    /// Reveal the secret and roll the wild Pokemon from the zone's spawn table. The seed mixes
+    /// the secret with the hash of the commit slot, so neither side can pick the outcome.
+    pub fn reveal_encounter(ctx: Context<RevealEncounter>, secret: [u8; 32]) -> Result<()> {
+        let encounter = &mut ctx.accounts.encounter;
+        require!(encounter.species_id.is_none(), ErrorCode::EncounterAlreadyRevealed);
+        require!(hash(&secret).to_bytes() == encounter.commitment, ErrorCode::InvalidEncounterSecret);
+        require!(Clock::get()?.slot > encounter.commit_slot, ErrorCode::EncounterNotReady);
+
+        let slot_hashes_data = ctx.accounts.slot_hashes.try_borrow_data()?;
+        let slot_hash = find_slot_hash(&slot_hashes_data, encounter.commit_slot)
+            .ok_or(ErrorCode::EncounterExpired)?;
+        let seed = hashv(&[&secret, &slot_hash, encounter.trainer.as_ref()]).to_bytes();
+
+        let (species_id, level) = roll_spawn(&ctx.accounts.zone.spawns, &seed);
+        let species = &ctx.accounts.species;
+        require!(species.species_id == species_id, ErrorCode::InvalidSpecies);
+
+        encounter.seed = seed;
+        encounter.species_id = Some(species_id);
+        encounter.level = level;
+        encounter.hp = calculate_stat(species.data.base_hp, level, 31);
+
+        emit!(WildPokemonAppeared {
+            trainer: encounter.trainer,
+            zone_id: encounter.zone_id,
+            species_id,
+            level,
+            commit_slot: encounter.commit_slot,
+            slot_hash,
+            seed,
+        });
+
+        Ok(())
+    }
+
+    /// Attack the wild Pokemon with one of your Pokemon to weaken it before a catch attempt.
+    /// The wild Pokemon fights back with its AI move; its move definitions are passed as
+    /// remaining accounts in move order.
+    pub fn attack_wild<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AttackWild<'info>>,
+        move_id: u16,
+    ) -> Result<()> {
+        let encounter = &mut ctx.accounts.encounter;
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(encounter.species_id.is_some() && !encounter.caught, ErrorCode::EncounterNotActive);
+        require!(encounter.hp > 0, ErrorCode::WildPokemonFainted);
+        require!(pokemon.trainer == encounter.trainer, ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active && pokemon.hp > 0, ErrorCode::PokemonNotAvailable);
+        require!(pokemon.moves.contains(&move_id), ErrorCode::UnknownMove);
+
+        let species = &ctx.accounts.species;
+        let mut wild = opponent_pokemon(species, encounter.level, encounter.hp);
+        let wild_moves = load_move_defs(ctx.remaining_accounts, &wild.moves)?;
+        let roll = ((Clock::get()?.slot + encounter.catch_attempts as u64) % 100) as u8;
+        let exchange = pve_exchange(
+            pokemon,
+            &mut wild,
+            &ctx.accounts.move_def,
+            &wild_moves,
+            [0; 6],
+            [0; 6],
+            roll,
+        );
+        encounter.hp = wild.hp;
+        if encounter.hp == 0 {
+            pokemon.experience += defeat_experience(encounter.level);
+        }
+
+        emit!(WildPokemonAttacked {
+            trainer: encounter.trainer,
+            pokemon_mint: pokemon.mint,
+            move_id,
+            damage: exchange.damage,
+            wild_hp: encounter.hp,
+            wild_move_id: exchange.opponent_move_id,
+            wild_damage: exchange.opponent_damage,
+        });
+
+        Ok(())
+    }
+
+    /// Throw a Poke Ball from the inventory, committing to `hash(secret)`. The chance is fixed
+    /// now and grows with the species catch rate, the ball's bonus and the HP the wild Pokemon
+    /// has lost; `reveal_throw` rolls it in a later slot. A throw left unrevealed until its
+    /// slot hash expires is a miss.
+    pub fn throw_ball(ctx: Context<ThrowBall>, commitment: [u8; 32]) -> Result<()> {
+        let encounter = &mut ctx.accounts.encounter;
+        let item = &ctx.accounts.item;
+        require!(encounter.species_id.is_some() && !encounter.caught, ErrorCode::EncounterNotActive);
+        require!(encounter.hp > 0, ErrorCode::WildPokemonFainted);
+        require!(item.kind == ItemKind::PokeBall, ErrorCode::ItemNotUsable);
+
+        let slot = Clock::get()?.slot;
+        if let Some(pending) = &encounter.pending_throw {
+            require!(slot > pending.commit_slot + MAX_ENTRIES as u64, ErrorCode::ThrowPending);
+        }
+        remove_from_inventory(&mut ctx.accounts.inventory, item.id, 1)?;
+
+        let species = &ctx.accounts.species;
+        let max_hp = calculate_stat(species.data.base_hp, encounter.level, 31);
+        encounter.pending_throw = Some(PendingThrow {
+            item_id: item.id,
+            chance: catch_chance(species.catch_rate, item.effect, encounter.hp, max_hp),
+            commitment,
+            commit_slot: slot,
+        });
+
+        Ok(())
+    }
+
+    /// Reveal a throw's secret and roll it against the chance fixed at the throw
+    pub fn reveal_throw(ctx: Context<RevealThrow>, secret: [u8; 32]) -> Result<()> {
+        let encounter = &mut ctx.accounts.encounter;
+        let pending = encounter.pending_throw.take().ok_or(ErrorCode::NoPendingThrow)?;
+        require!(hash(&secret).to_bytes() == pending.commitment, ErrorCode::InvalidEncounterSecret);
+        require!(Clock::get()?.slot > pending.commit_slot, ErrorCode::EncounterNotReady);
+
+        let slot_hashes_data = ctx.accounts.slot_hashes.try_borrow_data()?;
+        let slot_hash = find_slot_hash(&slot_hashes_data, pending.commit_slot)
+            .ok_or(ErrorCode::EncounterExpired)?;
+        let roll_hash = hashv(&[&secret, &slot_hash, &encounter.seed]).to_bytes();
+        let roll = (u64::from_le_bytes(roll_hash[..8].try_into().unwrap()) % 256) as u32;
+
+        encounter.catch_attempts = encounter.catch_attempts.saturating_add(1);
+        encounter.caught = roll < pending.chance;
+
+        emit!(CatchAttempted {
+            trainer: encounter.trainer,
+            species_id: encounter.species_id.unwrap_or_default(),
+            item_id: pending.item_id,
+            chance: pending.chance,
+            roll,
+            commit_slot: pending.commit_slot,
+            slot_hash,
+            caught: encounter.caught,
+        });
+
+        Ok(())
+    }
+
+    /// Mint the Pokemon from a successful catch and close the encounter
+    pub fn claim_wild_pokemon(ctx: Context<ClaimWildPokemon>) -> Result<()> {
+        let encounter = &ctx.accounts.encounter;
+        require!(encounter.caught, ErrorCode::PokemonNotCaught);
+        require!(
+            Some(ctx.accounts.catch.species.species_id) == encounter.species_id,
+            ErrorCode::InvalidSpecies
+        );
+
+        require!(
+            ctx.accounts.catch.trainer.public_key == encounter.trainer,
+            ErrorCode::PokemonNotOwned
+        );
+
+        let level = encounter.level;
+        let data = ctx.accounts.catch.species.data.clone();
+        let ability_seed = hashv(&[&encounter.seed, b"ability"]).to_bytes();
+        create_pokemon(&mut ctx.accounts.catch, ctx.bumps.catch.pokemon, data, level, ability_seed)?;
+        ctx.accounts.encounter.close(ctx.accounts.catch.trainer_authority.to_account_info())
+    }
+
+    /// Run from an encounter, or clear one that fainted or can no longer be revealed
+    pub fn flee_encounter(_ctx: Context<FleeEncounter>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Add an item to the shop registry
+    pub fn create_item(
+        ctx: Context<CreateItem>,
+        item_id: u16,
+        name: String,
+        kind: ItemKind,
+        effect: u16,
+        price: u64,
+        stock: Option<u32>,
+    ) -> Result<()> {
+        require!(!name.is_empty() && name.len() <= MAX_ITEM_NAME_LEN, ErrorCode::InvalidItemName);
+        require!(price > 0, ErrorCode::InvalidPrice);
+
+        let item = &mut ctx.accounts.item;
+        item.id = item_id;
+        item.name = name;
+        item.kind = kind;
+        item.effect = effect;
+        item.price = price;
+        item.stock = stock;
+        item.bump = ctx.bumps.item;
+
+        Ok(())
+    }
+
+    /// Reprice or restock a shop item; a `None` stock means unlimited
+    pub fn update_item(ctx: Context<UpdateItem>, price: u64, stock: Option<u32>) -> Result<()> {
+        require!(price > 0, ErrorCode::InvalidPrice);
+
+        let item = &mut ctx.accounts.item;
+        item.price = price;
+        item.stock = stock;
+
+        Ok(())
+    }
+
+    /// Buy items from the shop, paying PokeCoin to the treasury
+    pub fn buy_item(ctx: Context<BuyItem>, quantity: u32) -> Result<()> {
+        let item = &mut ctx.accounts.item;
+        require!(quantity > 0, ErrorCode::InvalidQuantity);
+        if let Some(stock) = item.stock {
+            require!(stock >= quantity, ErrorCode::ItemOutOfStock);
+            item.stock = Some(stock - quantity);
+        }
+
+        let cost = item.price.checked_mul(quantity as u64).ok_or(ErrorCode::MathOverflow)?;
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.buyer_coin_account.to_account_info(),
+            mint: ctx.accounts.poke_coin_mint.to_account_info(),
+            to: ctx.accounts.treasury_coin_account.to_account_info(),
+            authority: ctx.accounts.buyer.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, cost, ctx.accounts.poke_coin_mint.decimals)?;
+
+        let inventory = &mut ctx.accounts.inventory;
+        inventory.owner = ctx.accounts.buyer.key();
+        inventory.bump = ctx.bumps.inventory;
+        add_to_inventory(inventory, item.id, quantity)?;
+
+        emit!(ItemPurchased {
+            buyer: inventory.owner,
+            item_id: item.id,
+            quantity,
+            cost,
+        });
+
+        Ok(())
+    }
+
+    /// Challenge another trainer to a battle
+    pub fn challenge_trainer(
+        ctx: Context<ChallengeBattle>,
+        wager: u64,
+    ) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        let challenger = &ctx.accounts.challenger;
+        let game_state = &mut ctx.accounts.game_state;
+        require!(ctx.accounts.opponent.key() != challenger.public_key, ErrorCode::CannotBattleSelf);
+
+        battle.id = game_state.total_battles;
+        battle.challenger = challenger.public_key;
+        battle.opponent = ctx.accounts.opponent.key();
+        battle.wager = wager;
+        battle.state = BattleState::Challenged;
+        battle.current_turn = 0;
+        battle.challenger_active_pokemon = None;
+        battle.opponent_active_pokemon = None;
+        battle.item_limit = game_state.battle_item_limit;
+        battle.challenger_items_used = 0;
+        battle.opponent_items_used = 0;
+        battle.challenger_stat_stages = [0; 6];
+        battle.opponent_stat_stages = [0; 6];
+        battle.weather = None;
+        battle.weather_turns = 0;
+        battle.challenger_hazards = Hazards::default();
+        battle.opponent_hazards = Hazards::default();
+        battle.winner = None;
+        battle.created_at = Clock::get()?.unix_timestamp;
+        battle.last_action_at = battle.created_at;
+        battle.bump = ctx.bumps.battle;
+
+        game_state.total_battles += 1;
+
+        // Escrow the challenger's wager until the battle is settled or cancelled
+        if wager > 0 {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.challenger_coin_account.to_account_info(),
+                mint: ctx.accounts.poke_coin_mint.to_account_info(),
+                to: ctx.accounts.wager_vault.to_account_info(),
+                authority: ctx.accounts.challenger_authority.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token_interface::transfer_checked(cpi_ctx, wager, ctx.accounts.poke_coin_mint.decimals)?;
+        }
+
+        emit!(BattleChallenge {
+            battle_id: battle.id,
+            challenger: battle.challenger,
+            opponent: battle.opponent,
+            wager,
+        });
+
+        Ok(())
+    }
+
+    /// Accept a battle challenge
+    pub fn accept_battle(ctx: Context<AcceptBattle>) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        
+        require!(battle.state == BattleState::Challenged, ErrorCode::BattleNotChallenged);
+        require!(battle.opponent == ctx.accounts.opponent.key(), ErrorCode::UnauthorizedOpponent);
+
+        battle.state = BattleState::Active;
+        battle.started_at = Some(Clock::get()?.unix_timestamp);
+        battle.last_action_at = battle.started_at.unwrap();
+
+        // Match the challenger's wager
+        if battle.wager > 0 {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.opponent_coin_account.to_account_info(),
+                mint: ctx.accounts.poke_coin_mint.to_account_info(),
+                to: ctx.accounts.wager_vault.to_account_info(),
+                authority: ctx.accounts.opponent.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token_interface::transfer_checked(cpi_ctx, battle.wager, ctx.accounts.poke_coin_mint.decimals)?;
+        }
+
+        emit!(BattleAccepted {
+            battle_id: battle.id,
+            started_at: battle.started_at.unwrap(),
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw a challenge the opponent has not accepted yet, refunding the wager
+    pub fn cancel_battle(ctx: Context<CancelBattle>) -> Result<()> {
+        let battle = &ctx.accounts.battle;
+        require!(battle.state == BattleState::Challenged, ErrorCode::BattleNotChallenged);
+
+        let id_bytes = battle.id.to_le_bytes();
+        let seeds = &[
+            b"battle".as_ref(),
+            id_bytes.as_ref(),
+            &[battle.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let authority = battle.to_account_info();
+        let challenger = ctx.accounts.challenger_authority.to_account_info();
+
+        let refund = ctx.accounts.wager_vault.amount;
+        if refund > 0 {
+            pda_transfer(
+                &ctx.accounts.wager_vault,
+                &ctx.accounts.challenger_coin_account,
+                &ctx.accounts.poke_coin_mint,
+                &authority,
+                signer,
+                refund,
+                &ctx.accounts.token_program,
+            )?;
+        }
+        close_coin_vault(
+            &ctx.accounts.wager_vault,
+            &ctx.accounts.poke_coin_mint,
+            &challenger,
+            &authority,
+            signer,
+            &ctx.accounts.token_program,
+        )?;
+
+        Ok(())
+    }
+
+    /// Select Pokemon for battle: the first pick is free, later switches use the turn
+    pub fn select_pokemon(
+        ctx: Context<SelectPokemon>,
+        pokemon_mint: Pubkey,
+    ) -> Result<()> {
+        let battle_key = ctx.accounts.battle.key();
+        let battle: &mut Battle = &mut ctx.accounts.battle;
+        let trainer = &ctx.accounts.trainer;
+        let pokemon = &mut ctx.accounts.pokemon;
+
+        require!(battle.state == BattleState::Active, ErrorCode::BattleNotActive);
+        require!(pokemon.mint == pokemon_mint, ErrorCode::InvalidPokemonMint);
+        match &ctx.accounts.rental {
+            // A borrower battles with a rented Pokemon until the rental expires
+            Some(rental) if rental.borrower == Some(trainer.public_key) => {
+                require!(Clock::get()?.unix_timestamp < rental.expires_at, ErrorCode::RentalExpired);
+            }
+            _ => {
+                require!(pokemon.trainer == trainer.public_key, ErrorCode::PokemonNotOwned);
+                require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+            }
+        }
+
+        require!(pokemon.hp > 0, ErrorCode::PokemonNotAvailable);
+
+        let is_challenger = if battle.challenger == trainer.public_key {
+            true
+        } else if battle.opponent == trainer.public_key {
+            false
+        } else {
+            return Err(ErrorCode::TrainerNotInBattle.into());
+        };
+        let active_pokemon = if is_challenger {
+            battle.challenger_active_pokemon
+        } else {
+            battle.opponent_active_pokemon
+        };
+        require!(active_pokemon != Some(pokemon_mint), ErrorCode::PokemonAlreadyActive);
+
+        // Sending out the first Pokemon is free; switching uses the trainer's turn
+        if active_pokemon.is_some() {
+            require!((battle.current_turn % 2 == 0) == is_challenger, ErrorCode::NotYourTurn);
+            battle.current_turn += 1;
+        }
+        battle.last_action_at = Clock::get()?.unix_timestamp;
+        pokemon.battle = Some(battle_key);
+
+        if is_challenger {
+            battle.challenger_active_pokemon = Some(pokemon_mint);
+            battle.challenger_stat_stages = [0; 6];
+            apply_entry_hazards(pokemon, &battle.challenger_hazards);
+            on_switch_in(pokemon, &mut battle.opponent_stat_stages);
+        } else {
+            battle.opponent_active_pokemon = Some(pokemon_mint);
+            battle.opponent_stat_stages = [0; 6];
+            apply_entry_hazards(pokemon, &battle.opponent_hazards);
+            on_switch_in(pokemon, &mut battle.challenger_stat_stages);
+        }
+
+        emit!(PokemonSelected {
+            battle_id: battle.id,
+            trainer: trainer.public_key,
+            pokemon_mint,
+        });
+
+        Ok(())
+    }
+
+    /// Execute a move in battle
+    pub fn use_move(
+        ctx: Context<UseMove>,
+        move_id: u16,
+    ) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        let attacker_pokemon = &mut ctx.accounts.attacker_pokemon;
+        let defender_pokemon = &mut ctx.accounts.defender_pokemon;
+
+        require!(battle.state == BattleState::Active, ErrorCode::BattleNotActive);
+        
+        // Verify it's the correct trainer's turn
+        let is_challenger_turn = battle.current_turn % 2 == 0;
+        let attacker_key = ctx.accounts.trainer.key();
+        
+        if is_challenger_turn {
+            require!(battle.challenger == attacker_key, ErrorCode::NotYourTurn);
+            require!(Some(attacker_pokemon.mint) == battle.challenger_active_pokemon, ErrorCode::InvalidActivePokemon);
+            require!(Some(defender_pokemon.mint) == battle.opponent_active_pokemon, ErrorCode::InvalidActivePokemon);
+        } else {
+            require!(battle.opponent == attacker_key, ErrorCode::NotYourTurn);
+            require!(Some(attacker_pokemon.mint) == battle.opponent_active_pokemon, ErrorCode::InvalidActivePokemon);
+            require!(Some(defender_pokemon.mint) == battle.challenger_active_pokemon, ErrorCode::InvalidActivePokemon);
+        }
+        require!(attacker_pokemon.moves.contains(&move_id), ErrorCode::UnknownMove);
+        let move_def = &ctx.accounts.move_def;
+
+        // Calculate damage (simplified); moves without power only have an effect
+        let (attacker_stages, defender_stages) = if is_challenger_turn {
+            (battle.challenger_stat_stages, battle.opponent_stat_stages)
+        } else {
+            (battle.opponent_stat_stages, battle.challenger_stat_stages)
+        };
+        let damage = if move_def.power > 0 {
+            let damage = calculate_damage(
+                attacker_pokemon, 
+                defender_pokemon, 
+                move_def.power,
+                attacker_stages[1],
+                defender_stages[2],
+            );
+            modify_damage(attacker_pokemon, defender_pokemon, &move_def.move_type, &battle.weather, damage)
+        } else {
+            0
+        };
+
+        take_damage(defender_pokemon, damage);
+        if damage > 0 {
+            let roll = ((Clock::get()?.slot + battle.current_turn as u64) % 100) as u8;
+            on_hit(defender_pokemon, attacker_pokemon, roll);
+        }
+
+        // Field effects: weather covers the whole battle, hazards land on the defender's side
+        let defender_hazards = if is_challenger_turn {
+            &mut battle.opponent_hazards
+        } else {
+            &mut battle.challenger_hazards
+        };
+        match &move_def.effect {
+            Some(MoveEffect::Weather(weather)) => {
+                battle.weather = Some(weather.clone());
+                battle.weather_turns = WEATHER_TURNS;
+            }
+            Some(MoveEffect::StealthRock) => defender_hazards.stealth_rock = true,
+            Some(MoveEffect::Spikes) => defender_hazards.spikes = (defender_hazards.spikes + 1).min(MAX_SPIKES),
+            None => {}
+        }
+
+        end_of_turn(battle, attacker_pokemon);
+
+        battle.current_turn += 1;
+        battle.last_action_at = Clock::get()?.unix_timestamp;
+
+        emit!(MoveUsed {
+            battle_id: battle.id,
+            trainer: attacker_key,
+            pokemon_mint: attacker_pokemon.mint,
+            move_id,
+            damage,
+        });
+
+        // Check if Pokemon fainted
+        if defender_pokemon.hp == 0 {
+            attacker_pokemon.experience += defeat_experience(defender_pokemon.level);
+            // Simplified: Battle ends when one Pokemon faints
+            end_battle(battle, if is_challenger_turn { 0 } else { 1 })?;
+        } else if attacker_pokemon.hp == 0 {
+            // Fainted to weather damage at the end of its own turn
+            defender_pokemon.experience += defeat_experience(attacker_pokemon.level);
+            end_battle(battle, if is_challenger_turn { 1 } else { 0 })?;
+        }
+
+        Ok(())
+    }
+
+    /// Give a held item from the inventory to one of your Pokemon
+    pub fn give_item(ctx: Context<GiveItem>) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        let item = &ctx.accounts.item;
+        require!(pokemon.trainer == ctx.accounts.owner.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+        require!(pokemon.held_item.is_none(), ErrorCode::AlreadyHoldingItem);
+        require!(item.kind == ItemKind::HeldItem, ErrorCode::ItemNotUsable);
+
+        pokemon.held_item = Some(HeldItem {
+            item_id: item.id,
+            effect: held_item_effect_from_code(item.effect)?,
+        });
+        remove_from_inventory(&mut ctx.accounts.inventory, item.id, 1)?;
+
+        emit!(HeldItemChanged {
+            pokemon_mint: pokemon.mint,
+            item_id: Some(item.id),
+        });
+
+        Ok(())
+    }
+
+    /// Take a Pokemon's held item back into the inventory
+    pub fn take_item(ctx: Context<TakeItem>) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(pokemon.trainer == ctx.accounts.owner.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+
+        let held_item = pokemon.held_item.take().ok_or(ErrorCode::NotHoldingItem)?;
+        add_to_inventory(&mut ctx.accounts.inventory, held_item.item_id, 1)?;
+
+        emit!(HeldItemChanged {
+            pokemon_mint: pokemon.mint,
+            item_id: None,
+        });
+
+        Ok(())
+    }
+
+    /// Use a healing item on one of your Pokemon outside of battle
+    pub fn use_item(ctx: Context<UseItem>) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        let item = &ctx.accounts.item;
+        require!(pokemon.trainer == ctx.accounts.owner.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+        require!(!pve_battle_active(&ctx.accounts.pve_battle)?, ErrorCode::PveBattleInProgress);
+
+        apply_healing_item(pokemon, item)?;
+        remove_from_inventory(&mut ctx.accounts.inventory, item.id, 1)?;
+
+        emit!(ItemUsed {
+            trainer: ctx.accounts.owner.key(),
+            pokemon_mint: pokemon.mint,
+            item_id: item.id,
+            battle_id: None,
+        });
+
+        Ok(())
+    }
+
+    /// Use an item on your active Pokemon instead of a move; it takes up the turn
+    pub fn use_battle_item(ctx: Context<UseBattleItem>) -> Result<()> {
+        let battle: &mut Battle = &mut ctx.accounts.battle;
+        let pokemon = &mut ctx.accounts.pokemon;
+        let item = &ctx.accounts.item;
+
+        require!(battle.state == BattleState::Active, ErrorCode::BattleNotActive);
+
+        let is_challenger_turn = battle.current_turn % 2 == 0;
+        let item_limit = battle.item_limit;
+        let trainer_key = ctx.accounts.trainer.key();
+        let (items_used, stat_stages) = if is_challenger_turn {
+            require!(battle.challenger == trainer_key, ErrorCode::NotYourTurn);
+            require!(Some(pokemon.mint) == battle.challenger_active_pokemon, ErrorCode::InvalidActivePokemon);
+            (&mut battle.challenger_items_used, &mut battle.challenger_stat_stages)
+        } else {
+            require!(battle.opponent == trainer_key, ErrorCode::NotYourTurn);
+            require!(Some(pokemon.mint) == battle.opponent_active_pokemon, ErrorCode::InvalidActivePokemon);
+            (&mut battle.opponent_items_used, &mut battle.opponent_stat_stages)
+        };
+        require!(*items_used < item_limit, ErrorCode::BattleItemLimitReached);
+
+        if item.kind == ItemKind::BattleBoost {
+            // X items raise one stat (`effect` indexes it like the base stats) by a stage
+            let stage = stat_stages
+                .get_mut(item.effect as usize)
+                .ok_or(ErrorCode::ItemNotUsable)?;
+            require!(*stage < MAX_STAT_STAGE, ErrorCode::ItemHasNoEffect);
+            *stage += 1;
+        } else {
+            apply_healing_item(pokemon, item)?;
+        }
+        *items_used += 1;
+        remove_from_inventory(&mut ctx.accounts.inventory, item.id, 1)?;
+
+        end_of_turn(battle, pokemon);
+        battle.current_turn += 1;
+        battle.last_action_at = Clock::get()?.unix_timestamp;
+
+        emit!(ItemUsed {
+            trainer: trainer_key,
+            pokemon_mint: pokemon.mint,
+            item_id: item.id,
+            battle_id: Some(battle.id),
+        });
+
+        if pokemon.hp == 0 {
+            // Fainted to weather damage at the end of its own turn
+            end_battle(battle, if is_challenger_turn { 1 } else { 0 })?;
+        }
+
+        Ok(())
+    }
+
+    /// End a battle whose trainer to act has stalled past the turn deadline: the other trainer
+    /// wins and can settle it with `end_battle_and_reward`. Anyone can call this.
+    pub fn claim_battle_timeout(ctx: Context<ClaimBattleTimeout>) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        require!(battle.state == BattleState::Active, ErrorCode::BattleNotActive);
+        require!(
+            Clock::get()?.unix_timestamp > battle.last_action_at + BATTLE_TURN_TIMEOUT,
+            ErrorCode::TurnNotTimedOut
+        );
+
+        let stalling = stalling_side(battle);
+        end_battle(battle, 1 - stalling)
+    }
+
+    /// Settle a finished battle: the winner takes both wagers plus a PokeCoin reward from the
+    /// treasury, bounded by what is left of the season's emission budget
+    pub fn end_battle_and_reward(ctx: Context<EndBattle>) -> Result<()> {
+        let battle = &ctx.accounts.battle;
+        require!(battle.state == BattleState::Finished, ErrorCode::BattleNotFinished);
+
+        let (winner, loser) = match battle.winner {
+            Some(0) => (battle.challenger, battle.opponent),
+            Some(_) => (battle.opponent, battle.challenger),
+            None => return Err(ErrorCode::BattleNotFinished.into()),
+        };
+        require!(
+            ctx.accounts.winner_trainer.public_key == winner
+                && ctx.accounts.loser_trainer.public_key == loser,
+            ErrorCode::TrainerNotInBattle
+        );
+
+        let id_bytes = battle.id.to_le_bytes();
+        let seeds = &[
+            b"battle".as_ref(),
+            id_bytes.as_ref(),
+            &[battle.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let authority = battle.to_account_info();
+        let challenger = ctx.accounts.challenger_authority.to_account_info();
+        let token_program = &ctx.accounts.token_program;
+
+        // The winner takes the pot: the vault balance, net of any PokeCoin transfer fee
+        let pot = ctx.accounts.wager_vault.amount;
+        if pot > 0 {
+            pda_transfer(
+                &ctx.accounts.wager_vault,
+                &ctx.accounts.winner_coin_account,
+                &ctx.accounts.poke_coin_mint,
+                &authority,
+                signer,
+                pot,
+                token_program,
+            )?;
+        }
+        close_coin_vault(
+            &ctx.accounts.wager_vault,
+            &ctx.accounts.poke_coin_mint,
+            &challenger,
+            &authority,
+            signer,
+            token_program,
+        )?;
+
+        // Battle reward, capped by the season budget and whatever the treasury holds
+        let game_state = &ctx.accounts.game_state;
+        let reward = battle_reward(&ctx.accounts.winner_trainer, &ctx.accounts.loser_trainer)
+            .checked_mul(10u64.pow(ctx.accounts.poke_coin_mint.decimals as u32))
+            .ok_or(ErrorCode::MathOverflow)?
+            .min(game_state.season_emission_cap.saturating_sub(game_state.season_emitted))
+            .min(ctx.accounts.treasury_coin_account.amount);
+        if reward > 0 {
+            let seeds = &[
+                b"game_state".as_ref(),
+                &[game_state.bump],
+            ];
+            pda_transfer(
+                &ctx.accounts.treasury_coin_account,
+                &ctx.accounts.winner_coin_account,
+                &ctx.accounts.poke_coin_mint,
+                &game_state.to_account_info(),
+                &[&seeds[..]],
+                reward,
+                token_program,
+            )?;
+        }
+        ctx.accounts.game_state.season_emitted += reward;
+
+        // Update trainer stats
+        let winner_trainer = &mut ctx.accounts.winner_trainer;
+        winner_trainer.battles_won += 1;
+        winner_trainer.experience += 100;
+        ctx.accounts.loser_trainer.battles_lost += 1;
+
+        emit!(BattleRewardsDistributed {
+            battle_id: battle.id,
+            winner,
+            loser,
+            wager: battle.wager,
+            reward,
+            season: ctx.accounts.game_state.season,
+        });
+
+        Ok(())
+    }
+
+    /// Define an NPC trainer's team for PvE battles
+    pub fn create_npc_trainer(
+        ctx: Context<CreateNpcTrainer>,
+        npc_id: u16,
+        name: String,
+        team: Vec<NpcPokemon>,
+    ) -> Result<()> {
+        require!(!name.is_empty() && name.len() <= MAX_NPC_NAME_LEN, ErrorCode::InvalidNpcTeam);
+        validate_npc_team(&team)?;
+
+        let npc = &mut ctx.accounts.npc;
+        npc.npc_id = npc_id;
+        npc.name = name;
+        npc.team = team;
+        npc.bump = ctx.bumps.npc;
+
+        Ok(())
+    }
+
+    /// Start a PvE battle against an NPC trainer's team. The battle copies the team, so later
+    /// changes to the NPC don't affect it.
+    pub fn start_npc_battle(ctx: Context<StartNpcBattle>, npc_id: u16) -> Result<()> {
+        let battle = &mut ctx.accounts.pve_battle;
+        battle.trainer = ctx.accounts.owner.key();
+        battle.opponent = PveOpponent::Npc(npc_id);
+        battle.opponent_team = ctx.accounts.npc.team.clone();
+        start_pve_battle(battle, &ctx.accounts.trainer, None, ctx.bumps.pve_battle)
+    }
+
+    /// Attack in a PvE battle with your active Pokemon; the first attack sends it out. The
+    /// opponent answers with its AI move, whose definitions are passed as remaining accounts in
+    /// move order. Moves have no field effects in PvE.
+    pub fn pve_move<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PveMove<'info>>,
+        move_id: u16,
+    ) -> Result<()> {
+        let battle = &mut ctx.accounts.pve_battle;
+        let pokemon = &mut ctx.accounts.pokemon;
+        check_pve_pokemon(battle, pokemon)?;
+        require!(pokemon.moves.contains(&move_id), ErrorCode::UnknownMove);
+        match battle.active_pokemon {
+            Some(active) => require!(active == pokemon.mint, ErrorCode::InvalidActivePokemon),
+            None => {
+                battle.active_pokemon = Some(pokemon.mint);
+                on_switch_in(pokemon, &mut battle.opponent_stat_stages);
+            }
+        }
+
+        let (member, mut opponent) = pve_opponent(battle, &ctx.accounts.opponent_species)?;
+        let opponent_moves = load_move_defs(ctx.remaining_accounts, &opponent.moves)?;
+        let roll = ((Clock::get()?.slot + battle.turn as u64) % 100) as u8;
+        let exchange = pve_exchange(
+            pokemon,
+            &mut opponent,
+            &ctx.accounts.move_def,
+            &opponent_moves,
+            battle.trainer_stat_stages,
+            battle.opponent_stat_stages,
+            roll,
+        );
+        battle.turn += 1;
+
+        emit!(PveMoveUsed {
+            trainer: battle.trainer,
+            pokemon_mint: pokemon.mint,
+            move_id,
+            damage: exchange.damage,
+            opponent_species_id: member.species_id,
+            opponent_move_id: exchange.opponent_move_id,
+            opponent_damage: exchange.opponent_damage,
+        });
+
+        if opponent.hp > 0 {
+            battle.opponent_hp = Some(opponent.hp);
+            return pve_after_opponent_turn(battle, pokemon);
+        }
+
+        // Opponent fainted: the next one is sent out, or the battle is won
+        pokemon.experience += defeat_experience(member.level);
+        battle.opponent_hp = None;
+        battle.opponent_index += 1;
+        battle.opponent_stat_stages = [0; 6];
+        if battle.opponent_index as usize == battle.opponent_team.len() {
+            finish_pve_battle(battle, true);
+            return Ok(());
+        }
+
+        pve_after_opponent_turn(battle, pokemon)
+    }
+
+    /// Switch in another Pokemon from your team. Replacing a fainted Pokemon is free; otherwise
+    /// the switch uses up the turn and the opponent attacks the Pokemon coming in.
+    pub fn pve_switch<'info>(ctx: Context<'_, '_, 'info, 'info, PveSwitch<'info>>) -> Result<()> {
+        let battle = &mut ctx.accounts.pve_battle;
+        let pokemon = &mut ctx.accounts.pokemon;
+        check_pve_pokemon(battle, pokemon)?;
+        let previous = battle.active_pokemon;
+        require!(previous != Some(pokemon.mint), ErrorCode::PokemonAlreadyActive);
+
+        battle.active_pokemon = Some(pokemon.mint);
+        battle.trainer_stat_stages = [0; 6];
+        battle.opponent_stat_stages = [0; 6];
+        on_switch_in(pokemon, &mut battle.opponent_stat_stages);
+
+        let free = previous.map_or(true, |mint| battle.fainted.contains(&mint));
+        let (member, mut opponent) = pve_opponent(battle, &ctx.accounts.opponent_species)?;
+        let (opponent_move_id, opponent_damage) = if free {
+            (None, 0)
+        } else {
+            let opponent_moves = load_move_defs(ctx.remaining_accounts, &opponent.moves)?;
+            battle.turn += 1;
+            opponent_attack(
+                &mut opponent,
+                pokemon,
+                &opponent_moves,
+                battle.opponent_stat_stages,
+                battle.trainer_stat_stages,
+            )
+        };
+
+        emit!(PveSwitched {
+            trainer: battle.trainer,
+            pokemon_mint: pokemon.mint,
+            opponent_species_id: member.species_id,
+            opponent_move_id,
+            opponent_damage,
+        });
+
+        pve_after_opponent_turn(battle, pokemon)
+    }
+
+    /// Define a gym: its leader's team, the level cap for challengers, and the badge and
+    /// PokeCoin reward for beating it
+    pub fn create_gym(
+        ctx: Context<CreateGym>,
+        gym_id: u16,
+        leader_team: Vec<NpcPokemon>,
+        level_cap: u8,
+        type_specialty: PokemonType,
+        badge_id: u8,
+        reward: u64,
+    ) -> Result<()> {
+        validate_npc_team(&leader_team)?;
+        require!(level_cap > 0 && level_cap <= MAX_LEVEL, ErrorCode::InvalidGym);
+
+        let gym = &mut ctx.accounts.gym;
+        gym.gym_id = gym_id;
+        gym.leader_team = leader_team;
+        gym.level_cap = level_cap;
+        gym.type_specialty = type_specialty;
+        gym.badge_id = badge_id;
+        gym.reward = reward;
+        gym.bump = ctx.bumps.gym;
+
+        let badge = &mut ctx.accounts.badge;
+        badge.badge_id = badge_id;
+        badge.gym_id = gym_id;
+        badge.bump = ctx.bumps.badge;
+
+        Ok(())
+    }
+
+    /// Challenge a gym leader: a PvE battle against the leader's team where only Pokemon at or
+    /// below the gym's level cap can fight
+    pub fn challenge_gym(ctx: Context<ChallengeGym>, gym_id: u16) -> Result<()> {
+        let gym = &ctx.accounts.gym;
+        require!(
+            !ctx.accounts.trainer.badges.contains(&gym.badge_id),
+            ErrorCode::BadgeAlreadyEarned
+        );
+
+        let battle = &mut ctx.accounts.pve_battle;
+        battle.trainer = ctx.accounts.owner.key();
+        battle.opponent = PveOpponent::Gym(gym_id);
+        battle.opponent_team = gym.leader_team.clone();
+        start_pve_battle(battle, &ctx.accounts.trainer, Some(gym.level_cap), ctx.bumps.pve_battle)
+    }
+
+    /// Award the badge and reward for a won gym battle and close the battle
+    pub fn claim_gym_badge(ctx: Context<ClaimGymBadge>) -> Result<()> {
+        let battle = &ctx.accounts.pve_battle;
+        let gym = &ctx.accounts.gym;
+        require!(battle.state == BattleState::Finished && battle.won, ErrorCode::BattleNotWon);
+        require!(battle.opponent == PveOpponent::Gym(gym.gym_id), ErrorCode::InvalidGym);
+
+        let trainer = &mut ctx.accounts.trainer;
+        require!(!trainer.badges.contains(&gym.badge_id), ErrorCode::BadgeAlreadyEarned);
+        require!(trainer.badges.len() < MAX_BADGES, ErrorCode::TooManyBadges);
+        trainer.badges.push(gym.badge_id);
+
+        let game_state = &ctx.accounts.game_state;
+        let seeds = &[
+            b"game_state".as_ref(),
+            &[game_state.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Gym rewards come out of the same season budget as battle rewards
+        let reward = gym
+            .reward
+            .checked_mul(10u64.pow(ctx.accounts.poke_coin_mint.decimals as u32))
+            .ok_or(ErrorCode::MathOverflow)?
+            .min(game_state.season_emission_cap.saturating_sub(game_state.season_emitted));
+        if reward > 0 {
+            pda_transfer(
+                &ctx.accounts.treasury_coin_account,
+                &ctx.accounts.owner_coin_account,
+                &ctx.accounts.poke_coin_mint,
+                &game_state.to_account_info(),
+                signer,
+                reward,
+                &ctx.accounts.token_program,
+            )?;
+        }
+        ctx.accounts.game_state.season_emitted += reward;
+
+        emit!(BadgeEarned {
+            trainer: trainer.public_key,
+            gym_id: gym.gym_id,
+            badge_id: gym.badge_id,
+            reward,
+        });
+
+        Ok(())
+    }
+
+    /// Close a PvE battle; leaving one that is still active forfeits it
+    pub fn end_pve_battle(ctx: Context<EndPveBattle>) -> Result<()> {
+        let battle = &ctx.accounts.pve_battle;
+        if battle.state == BattleState::Active {
+            emit!(PveBattleEnded {
+                trainer: battle.trainer,
+                opponent: battle.opponent.clone(),
+                won: false,
+                turns: battle.turn,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Update the marketplace protocol fee
+    pub fn set_marketplace_fee(ctx: Context<UpdateGameConfig>, fee_bps: u16) -> Result<()> {
+        require!(fee_bps <= MAX_FEE_BPS, ErrorCode::InvalidFee);
+        ctx.accounts.game_state.marketplace_fee_bps = fee_bps;
+        Ok(())
+    }
+
+    /// List a Pokemon NFT for sale at a fixed PokeCoin price, escrowing it in the listing
+    pub fn list_pokemon(ctx: Context<ListPokemon>, price: u64) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(price > 0, ErrorCode::InvalidPrice);
+        require!(pokemon.trainer == ctx.accounts.seller.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+
+        let listing = &mut ctx.accounts.listing;
+        listing.seller = ctx.accounts.seller.key();
+        listing.pokemon_mint = ctx.accounts.pokemon_mint.key();
+        listing.price = price;
+        listing.created_at = Clock::get()?.unix_timestamp;
+        listing.bump = ctx.bumps.listing;
+
+        pokemon.status = PokemonStatus::Listed;
+
+        // Move the NFT into escrow
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.seller_pokemon_account.to_account_info(),
+            mint: ctx.accounts.pokemon_mint.to_account_info(),
+            to: ctx.accounts.escrow_pokemon_account.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token_interface::transfer_checked(CpiContext::new(cpi_program, cpi_accounts), 1, 0)?;
+
+        emit!(PokemonListed {
+            seller: listing.seller,
+            pokemon_mint: listing.pokemon_mint,
+            price,
+        });
+
+        Ok(())
+    }
+
+    /// Cancel a listing and return the escrowed NFT to the seller
+    pub fn cancel_listing(ctx: Context<CancelListing>) -> Result<()> {
+        let listing = &ctx.accounts.listing;
+
+        release_listing_escrow(
+            listing,
+            &ctx.accounts.escrow_pokemon_account,
+            &ctx.accounts.seller_pokemon_account,
+            &ctx.accounts.pokemon_mint,
+            &ctx.accounts.seller,
+            &ctx.accounts.token_program,
+        )?;
+
+        ctx.accounts.pokemon.status = PokemonStatus::Active;
+
+        emit!(ListingCancelled {
+            seller: listing.seller,
+            pokemon_mint: listing.pokemon_mint,
+        });
+
+        Ok(())
+    }
+
+    /// Buy a listed Pokemon, paying the seller in PokeCoin minus the protocol fee
+    pub fn buy_pokemon(ctx: Context<BuyPokemon>) -> Result<()> {
+        let listing = &ctx.accounts.listing;
+        let price = listing.price;
+        require!(ctx.accounts.buyer.key() != listing.seller, ErrorCode::CannotBuyOwnListing);
+
+        let fee = protocol_fee(price, ctx.accounts.game_state.marketplace_fee_bps);
+
+        // Pay the seller and the treasury
+        let cpi_program = ctx.accounts.coin_token_program.to_account_info();
+        let decimals = ctx.accounts.poke_coin_mint.decimals;
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.buyer_coin_account.to_account_info(),
+            mint: ctx.accounts.poke_coin_mint.to_account_info(),
+            to: ctx.accounts.seller_coin_account.to_account_info(),
+            authority: ctx.accounts.buyer.to_account_info(),
+        };
+        token_interface::transfer_checked(CpiContext::new(cpi_program.clone(), cpi_accounts), price - fee, decimals)?;
+
+        if fee > 0 {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.buyer_coin_account.to_account_info(),
+                mint: ctx.accounts.poke_coin_mint.to_account_info(),
+                to: ctx.accounts.treasury_coin_account.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            };
+            token_interface::transfer_checked(CpiContext::new(cpi_program, cpi_accounts), fee, decimals)?;
+        }
+
+        // Deliver the NFT
+        release_listing_escrow(
+            listing,
+            &ctx.accounts.escrow_pokemon_account,
+            &ctx.accounts.buyer_pokemon_account,
+            &ctx.accounts.pokemon_mint,
+            &ctx.accounts.seller,
+            &ctx.accounts.token_program,
+        )?;
+
+        // Re-bind ownership
+        let pokemon_mint = ctx.accounts.pokemon_mint.key();
+        let pokemon = &mut ctx.accounts.pokemon;
+        pokemon.trainer = ctx.accounts.buyer.key();
+        pokemon.status = PokemonStatus::Active;
+        remove_from_collection(&mut ctx.accounts.seller_trainer, &pokemon_mint);
+        add_to_collection(&mut ctx.accounts.buyer_trainer, pokemon_mint)?;
+
+        emit!(PokemonSold {
+            seller: listing.seller,
+            buyer: ctx.accounts.buyer.key(),
+            pokemon_mint,
+            price,
+            fee,
+        });
+
+        Ok(())
+    }
+    /// Put a Pokemon up for an English auction, escrowing the NFT in the auction PDA
+    pub fn create_auction(
+        ctx: Context<CreateAuction>,
+        reserve_price: u64,
+        min_bid_increment: u64,
+        duration: i64,
+    ) -> Result<()> {
+        let pokemon = &mut ctx.accounts.pokemon;
+        require!(reserve_price > 0 && min_bid_increment > 0, ErrorCode::InvalidPrice);
+        require!(duration > 0 && duration <= MAX_AUCTION_DURATION, ErrorCode::InvalidAuctionDuration);
+        require!(pokemon.trainer == ctx.accounts.seller.key(), ErrorCode::PokemonNotOwned);
+        require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+
+        let now = Clock::get()?.unix_timestamp;
+        let auction = &mut ctx.accounts.auction;
+        auction.seller = ctx.accounts.seller.key();
+        auction.pokemon_mint = ctx.accounts.pokemon_mint.key();
+        auction.reserve_price = reserve_price;
+        auction.min_bid_increment = min_bid_increment;
+        auction.highest_bidder = None;
+        auction.highest_bid = 0;
+        auction.end_time = now + duration;
+        auction.created_at = now;
+        auction.bump = ctx.bumps.auction;
+
+        pokemon.status = PokemonStatus::Listed;
+
+        // Move the NFT into escrow
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.seller_pokemon_account.to_account_info(),
+            mint: ctx.accounts.pokemon_mint.to_account_info(),
+            to: ctx.accounts.escrow_pokemon_account.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        token_interface::transfer_checked(CpiContext::new(cpi_program, cpi_accounts), 1, 0)?;
+
+        emit!(AuctionCreated {
+            seller: auction.seller,
+            pokemon_mint: auction.pokemon_mint,
+            reserve_price,
+            min_bid_increment,
+            end_time: auction.end_time,
+        });
+
+        Ok(())
+    }
+
+    /// Bid on an auction. The bid is escrowed in the auction vault and the previous
+    /// highest bidder is refunded in the same instruction.
+    pub fn place_bid(ctx: Context<PlaceBid>, amount: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let auction = &ctx.accounts.auction;
+        require!(now < auction.end_time, ErrorCode::AuctionEnded);
+        require!(ctx.accounts.bidder.key() != auction.seller, ErrorCode::CannotBuyOwnListing);
+
+        match auction.highest_bidder {
+            Some(previous_bidder) => {
+                require!(
+                    amount >= auction.highest_bid.saturating_add(auction.min_bid_increment),
+                    ErrorCode::BidTooLow
+                );
+
+                // Refund the outbid bidder
+                let refund_account = ctx.accounts.previous_bidder_coin_account
+                    .as_ref()
+                    .ok_or(ErrorCode::MissingRefundAccount)?;
+                require!(refund_account.owner == previous_bidder, ErrorCode::MissingRefundAccount);
+
+                let seeds = &[
+                    b"auction".as_ref(),
+                    auction.pokemon_mint.as_ref(),
+                    &[auction.bump],
+                ];
+                // The vault holds less than the bid if PokeCoin charges a transfer fee
+                pda_transfer(
+                    &ctx.accounts.bid_vault,
+                    refund_account,
+                    &ctx.accounts.poke_coin_mint,
+                    &auction.to_account_info(),
+                    &[&seeds[..]],
+                    ctx.accounts.bid_vault.amount,
+                    &ctx.accounts.token_program,
+                )?;
+            }
+            None => require!(amount >= auction.reserve_price, ErrorCode::BidTooLow),
+        }
+
+        // Escrow the new bid
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.bidder_coin_account.to_account_info(),
+            mint: ctx.accounts.poke_coin_mint.to_account_info(),
+            to: ctx.accounts.bid_vault.to_account_info(),
+            authority: ctx.accounts.bidder.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let decimals = ctx.accounts.poke_coin_mint.decimals;
+        token_interface::transfer_checked(CpiContext::new(cpi_program, cpi_accounts), amount, decimals)?;
+
+        let auction = &mut ctx.accounts.auction;
+        auction.highest_bidder = Some(ctx.accounts.bidder.key());
+        auction.highest_bid = amount;
+
+        // Anti-sniping: a late bid pushes the end time back
+        if auction.end_time - now < AUCTION_EXTENSION {
+            auction.end_time = now + AUCTION_EXTENSION;
+        }
+
+        emit!(BidPlaced {
+            pokemon_mint: auction.pokemon_mint,
+            bidder: ctx.accounts.bidder.key(),
+            amount,
+            end_time: auction.end_time,
+        });
+
+        Ok(())
+    }
+
+    /// Settle an ended auction. Permissionless: anyone can crank it once the end time passes.
+    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        require!(Clock::get()?.unix_timestamp >= auction.end_time, ErrorCode::AuctionNotEnded);
+
+        let seeds = &[
+            b"auction".as_ref(),
+            auction.pokemon_mint.as_ref(),
+            &[auction.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let authority = auction.to_account_info();
+        let seller = ctx.accounts.seller.to_account_info();
+        let token_program = &ctx.accounts.token_program;
+        let coin_token_program = &ctx.accounts.coin_token_program;
+
+        // Proceeds are the vault balance, net of any PokeCoin transfer fee on the winning bid
+        let proceeds = ctx.accounts.bid_vault.amount;
+        let fee = protocol_fee(proceeds, ctx.accounts.game_state.marketplace_fee_bps);
+        if auction.highest_bidder.is_some() {
+            // Pay the seller and the treasury out of the bid vault
+            pda_transfer(
+                &ctx.accounts.bid_vault,
+                &ctx.accounts.seller_coin_account,
+                &ctx.accounts.poke_coin_mint,
+                &authority,
+                signer,
+                proceeds - fee,
+                coin_token_program,
+            )?;
+            if fee > 0 {
+                pda_transfer(
+                    &ctx.accounts.bid_vault,
+                    &ctx.accounts.treasury_coin_account,
+                    &ctx.accounts.poke_coin_mint,
+                    &authority,
+                    signer,
+                    fee,
+                    coin_token_program,
+                )?;
+            }
+        }
+
+        // The NFT goes to the winner, or back to the seller if nobody bid
+        pda_transfer(
+            &ctx.accounts.escrow_pokemon_account,
+            &ctx.accounts.recipient_pokemon_account,
+            &ctx.accounts.pokemon_mint,
+            &authority,
+            signer,
+            1,
+            token_program,
+        )?;
+        pda_close(&ctx.accounts.escrow_pokemon_account, &seller, &authority, signer, token_program)?;
+        close_coin_vault(
+            &ctx.accounts.bid_vault,
+            &ctx.accounts.poke_coin_mint,
+            &seller,
+            &authority,
+            signer,
+            coin_token_program,
+        )?;
+
+        let pokemon_mint = ctx.accounts.pokemon_mint.key();
+        let pokemon = &mut ctx.accounts.pokemon;
+        pokemon.status = PokemonStatus::Active;
+
+        if let Some(winner) = auction.highest_bidder {
+            pokemon.trainer = winner;
+            remove_from_collection(&mut ctx.accounts.seller_trainer, &pokemon_mint);
+            // A full team and box must not block settlement and lock the seller's proceeds:
+            // the winner still owns the Pokemon, it just isn't listed in their collection
+            if add_to_collection(&mut ctx.accounts.recipient_trainer, pokemon_mint).is_err() {
+                emit!(PokemonNotCollected {
+                    trainer: winner,
+                    pokemon_mint,
+                });
+            }
+        }
+
+        emit!(AuctionSettled {
+            seller: auction.seller,
+            winner: auction.highest_bidder,
+            pokemon_mint,
+            price: auction.highest_bid,
+            fee: if auction.highest_bidder.is_some() { fee } else { 0 },
+        });
+
+        Ok(())
+    }
+    /// Propose a trade: up to six of the proposer's Pokemon plus optional PokeCoin for
+    /// specific Pokemon of the recipient. The offered side is escrowed in the trade PDA.
+    ///
+    /// Remaining accounts, per offered mint: pokemon, pokemon_mint,
+    /// proposer_pokemon_account, escrow_pokemon_account (ATA of the trade PDA).
+    pub fn propose_trade<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ProposeTrade<'info>>,
+        offered_mints: Vec<Pubkey>,
+        requested_mints: Vec<Pubkey>,
+        coin_amount: u64,
+    ) -> Result<()> {
+        require!(
+            offered_mints.len() <= MAX_TRADE_POKEMON && requested_mints.len() <= MAX_TRADE_POKEMON,
+            ErrorCode::InvalidTrade
+        );
+        require!(!requested_mints.is_empty(), ErrorCode::InvalidTrade);
+        require!(!offered_mints.is_empty() || coin_amount > 0, ErrorCode::InvalidTrade);
+        require!(
+            has_unique_mints(&offered_mints) && has_unique_mints(&requested_mints),
+            ErrorCode::InvalidTrade
+        );
+        require!(
+            ctx.remaining_accounts.len() == offered_mints.len() * 4,
+            ErrorCode::InvalidTradeAccounts
+        );
+
+        let proposer = ctx.accounts.proposer.key();
+        let trade_info = ctx.accounts.trade.to_account_info();
+
+        // Escrow the offered Pokemon
+        for (accounts, mint) in ctx.remaining_accounts.chunks(4).zip(offered_mints.iter()) {
+            let (pokemon_info, mint_info, from, escrow) = (&accounts[0], &accounts[1], &accounts[2], &accounts[3]);
+
+            let mut pokemon = load_trade_pokemon(pokemon_info, mint_info, mint)?;
+            require!(pokemon.trainer == proposer, ErrorCode::PokemonNotOwned);
+            require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+            pokemon.status = PokemonStatus::InTrade;
+            pokemon.exit(&crate::ID)?;
+
+            create_ata_if_needed(
+                &ctx.accounts.proposer.to_account_info(),
+                escrow,
+                &trade_info,
+                mint_info,
+                &ctx.accounts.system_program,
+                &ctx.accounts.token_program,
+                &ctx.accounts.associated_token_program,
+            )?;
+
+            let cpi_accounts = TransferChecked {
+                from: from.clone(),
+                mint: mint_info.clone(),
+                to: escrow.clone(),
+                authority: ctx.accounts.proposer.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            token_interface::transfer_checked(CpiContext::new(cpi_program, cpi_accounts), 1, 0)?;
+        }
+
+        // Escrow the offered PokeCoin
+        if coin_amount > 0 {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.proposer_coin_account.to_account_info(),
+                mint: ctx.accounts.poke_coin_mint.to_account_info(),
+                to: ctx.accounts.coin_vault.to_account_info(),
+                authority: ctx.accounts.proposer.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.coin_token_program.to_account_info();
+            let decimals = ctx.accounts.poke_coin_mint.decimals;
+            token_interface::transfer_checked(CpiContext::new(cpi_program, cpi_accounts), coin_amount, decimals)?;
+        }
+
+        let trade = &mut ctx.accounts.trade;
+        trade.proposer = proposer;
+        trade.recipient = ctx.accounts.recipient.key();
+        trade.offered_mints = offered_mints;
+        trade.requested_mints = requested_mints;
+        trade.coin_amount = coin_amount;
+        trade.created_at = Clock::get()?.unix_timestamp;
+        trade.bump = ctx.bumps.trade;
+
+        emit!(TradeProposed {
+            proposer: trade.proposer,
+            recipient: trade.recipient,
+            offered_mints: trade.offered_mints.clone(),
+            requested_mints: trade.requested_mints.clone(),
+            coin_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Accept a trade. Both sides swap NFTs, `Pokemon.trainer` and team/box entries
+    /// in this one instruction.
+    ///
+    /// Remaining accounts, per offered mint: pokemon, pokemon_mint, escrow_pokemon_account,
+    /// recipient_pokemon_account; then per requested mint: pokemon, pokemon_mint,
+    /// recipient_pokemon_account, proposer_pokemon_account.
+    pub fn accept_trade<'info>(ctx: Context<'_, '_, 'info, 'info, AcceptTrade<'info>>) -> Result<()> {
+        let trade = &ctx.accounts.trade;
+        let offered_count = trade.offered_mints.len();
+        require!(
+            ctx.remaining_accounts.len() == (offered_count + trade.requested_mints.len()) * 4,
+            ErrorCode::InvalidTradeAccounts
+        );
+
+        let seeds = &[
+            b"trade".as_ref(),
+            trade.proposer.as_ref(),
+            trade.recipient.as_ref(),
+            &[trade.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let trade_info = trade.to_account_info();
+        let proposer_info = ctx.accounts.proposer.to_account_info();
+        let recipient_info = ctx.accounts.recipient.to_account_info();
+        let (offered_accounts, requested_accounts) = ctx.remaining_accounts.split_at(offered_count * 4);
+
+        // Offered Pokemon: escrow -> recipient
+        for (accounts, mint) in offered_accounts.chunks(4).zip(trade.offered_mints.iter()) {
+            let (pokemon_info, mint_info, escrow, to) = (&accounts[0], &accounts[1], &accounts[2], &accounts[3]);
+
+            let mut pokemon = load_trade_pokemon(pokemon_info, mint_info, mint)?;
+            pokemon.trainer = trade.recipient;
+            pokemon.status = PokemonStatus::Active;
+            pokemon.traded = true;
+            pokemon.exit(&crate::ID)?;
+
+            create_ata_if_needed(
+                &recipient_info,
+                to,
+                &recipient_info,
+                mint_info,
+                &ctx.accounts.system_program,
+                &ctx.accounts.token_program,
+                &ctx.accounts.associated_token_program,
+            )?;
+
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: escrow.clone(),
+                mint: mint_info.clone(),
+                to: to.clone(),
+                authority: trade_info.clone(),
+            };
+            token_interface::transfer_checked(CpiContext::new_with_signer(cpi_program.clone(), cpi_accounts, signer), 1, 0)?;
+            let cpi_accounts = CloseAccount {
+                account: escrow.clone(),
+                destination: proposer_info.clone(),
+                authority: trade_info.clone(),
+            };
+            token_interface::close_account(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer))?;
+
+            remove_from_collection(&mut ctx.accounts.proposer_trainer, mint);
+            add_to_collection(&mut ctx.accounts.recipient_trainer, *mint)?;
+
+            emit!(PokemonTraded {
+                pokemon_mint: *mint,
+                species_id: pokemon.species_id,
+                from: trade.proposer,
+                to: trade.recipient,
+            });
+        }
+
+        // Requested Pokemon: recipient -> proposer
+        for (accounts, mint) in requested_accounts.chunks(4).zip(trade.requested_mints.iter()) {
+            let (pokemon_info, mint_info, from, to) = (&accounts[0], &accounts[1], &accounts[2], &accounts[3]);
+
+            let mut pokemon = load_trade_pokemon(pokemon_info, mint_info, mint)?;
+            require!(pokemon.trainer == trade.recipient, ErrorCode::PokemonNotOwned);
+            require!(pokemon.status == PokemonStatus::Active, ErrorCode::PokemonNotAvailable);
+            pokemon.trainer = trade.proposer;
+            pokemon.traded = true;
+            pokemon.exit(&crate::ID)?;
+
+            create_ata_if_needed(
+                &recipient_info,
+                to,
+                &proposer_info,
+                mint_info,
+                &ctx.accounts.system_program,
+                &ctx.accounts.token_program,
+                &ctx.accounts.associated_token_program,
+            )?;
+
+            let cpi_accounts = TransferChecked {
+                from: from.clone(),
+                mint: mint_info.clone(),
+                to: to.clone(),
+                authority: recipient_info.clone(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            token_interface::transfer_checked(CpiContext::new(cpi_program, cpi_accounts), 1, 0)?;
+
+            remove_from_collection(&mut ctx.accounts.recipient_trainer, mint);
+            add_to_collection(&mut ctx.accounts.proposer_trainer, *mint)?;
+
+            emit!(PokemonTraded {
+                pokemon_mint: *mint,
+                species_id: pokemon.species_id,
+                from: trade.recipient,
+                to: trade.proposer,
+            });
+        }
+
+        // Escrowed PokeCoin -> recipient. Pays out the vault balance, which is less than
+        // `coin_amount` when PokeCoin charges a transfer fee.
+        let escrowed_coins = ctx.accounts.coin_vault.amount;
+        if escrowed_coins > 0 {
+            pda_transfer(
+                &ctx.accounts.coin_vault,
+                &ctx.accounts.recipient_coin_account,
+                &ctx.accounts.poke_coin_mint,
+                &trade_info,
+                signer,
+                escrowed_coins,
+                &ctx.accounts.coin_token_program,
+            )?;
+        }
+        close_coin_vault(
+            &ctx.accounts.coin_vault,
+            &ctx.accounts.poke_coin_mint,
+            &proposer_info,
+            &trade_info,
+            signer,
+            &ctx.accounts.coin_token_program,
+        )?;
+
+        emit!(TradeCompleted {
+            proposer: trade.proposer,
+            recipient: trade.recipient,
+        });
+
+        Ok(())
+    }
+
+    /// Reject a trade offer as the recipient, returning the escrow to the proposer
+    ///
+    /// Remaining accounts, per offered mint: pokemon, pokemon_mint,
+    /// escrow_pokemon_account, proposer_pokemon_account.
+    pub fn reject_trade<'info>(ctx: Context<'_, '_, 'info, 'info, CloseTrade<'info>>) -> Result<()> {
+        require!(ctx.accounts.authority.key() == ctx.accounts.trade.recipient, ErrorCode::UnauthorizedTradeParty);
+        return_trade_escrow(&ctx)
+    }
+
+    /// Withdraw a trade offer as the proposer, returning the escrow
+    ///
+    /// Remaining accounts as in `reject_trade`.
+    pub fn cancel_trade<'info>(ctx: Context<'_, '_, 'info, 'info, CloseTrade<'info>>) -> Result<()> {
+        require!(ctx.accounts.authority.key() == ctx.accounts.trade.proposer, ErrorCode::UnauthorizedTradeParty);
+        return_trade_escrow(&ctx)
+    }
+}
+
+// Helper functions
+fn calculate_stat(base_stat: u16, level: u8, iv: u8) -> u16 {
+    ((2 * base_stat as u32 + iv as u32) * level as u32 / 100 + 5) as u16
+}
+
+fn base_stats(data: &PokemonData) -> [u16; 6] {
+    [
+        data.base_hp,
+        data.base_attack,
+        data.base_defense,
+        data.base_sp_attack,
+        data.base_sp_defense,
+        data.base_speed,
+    ]
+}
+
+fn apply_evolution(pokemon: &mut Pokemon, evolution: &PokemonData) {
+    pokemon.species_id = evolution.species_id;
+    pokemon.name = evolution.name.clone();
+    pokemon.base_stats = base_stats(evolution);
+    pokemon.types = evolution.types.clone();
+    recalculate_stats(pokemon);
+}
+
+fn recalculate_stats(pokemon: &mut Pokemon) {
+    let [hp, attack, defense, sp_attack, sp_defense, speed] = pokemon.base_stats;
+    pokemon.hp = calculate_stat(hp, pokemon.level, 31); // Perfect IVs for now
+    pokemon.attack = calculate_stat(attack, pokemon.level, 31);
+    pokemon.defense = calculate_stat(defense, pokemon.level, 31);
+    pokemon.sp_attack = calculate_stat(sp_attack, pokemon.level, 31);
+    pokemon.sp_defense = calculate_stat(sp_defense, pokemon.level, 31);
+    pokemon.speed = calculate_stat(speed, pokemon.level, 31);
+}
+
+/// Scale a stat by its battle stage (-6..=6): each stage up adds half the stat, each stage
+/// down divides it further
+fn apply_stat_stage(stat: u16, stage: i8) -> u32 {
+    let stat = stat as u32;
+    if stage >= 0 {
+        stat * (2 + stage as u32) / 2
+    } else {
+        stat * 2 / (2 + stage.unsigned_abs() as u32)
+    }
+}
+
+fn max_hp(pokemon: &Pokemon) -> u16 {
+    calculate_stat(pokemon.base_stats[0], pokemon.level, 31)
+}
+
+/// Apply a healing item (potion, revive or status heal) to a Pokemon
+fn apply_healing_item(pokemon: &mut Pokemon, item: &Item) -> Result<()> {
+    let max_hp = max_hp(pokemon);
+    match item.kind {
+        ItemKind::Potion => {
+            require!(pokemon.hp > 0 && pokemon.hp < max_hp, ErrorCode::ItemHasNoEffect);
+            pokemon.hp = pokemon.hp.saturating_add(item.effect).min(max_hp);
+        }
+        ItemKind::Revive => {
+            require!(pokemon.hp == 0, ErrorCode::ItemHasNoEffect);
+            pokemon.hp = item.effect.clamp(1, max_hp);
+            pokemon.condition = None;
+        }
+        ItemKind::StatusHeal => {
+            require!(pokemon.condition.is_some(), ErrorCode::ItemHasNoEffect);
+            pokemon.condition = None;
+        }
+        _ => return Err(ErrorCode::ItemNotUsable.into()),
+    }
+    Ok(())
+}
+
+fn held_item_effect(pokemon: &Pokemon) -> Option<HeldItemEffect> {
+    pokemon.held_item.as_ref().map(|held_item| held_item.effect.clone())
+}
+
+/// Held items store their passive effect as the item's `effect` code
+fn held_item_effect_from_code(code: u16) -> Result<HeldItemEffect> {
+    match code {
+        0 => Ok(HeldItemEffect::Leftovers),
+        1 => Ok(HeldItemEffect::ChoiceBand),
+        2 => Ok(HeldItemEffect::Berry),
+        3 => Ok(HeldItemEffect::FocusSash),
+        _ => Err(ErrorCode::ItemNotUsable.into()),
+    }
+}
+
+/// Deal damage to a Pokemon and let its held item react: a Focus Sash survives a knockout
+/// blow at full HP, a berry restores a quarter of max HP once HP drops to half. Both are used up.
+fn take_damage(pokemon: &mut Pokemon, damage: u16) {
+    let max_hp = max_hp(pokemon);
+    match held_item_effect(pokemon) {
+        Some(HeldItemEffect::FocusSash) if pokemon.hp >= max_hp && damage >= pokemon.hp => {
+            pokemon.hp = 1;
+            consume_held_item(pokemon);
+        }
+        Some(HeldItemEffect::Berry) => {
+            pokemon.hp = pokemon.hp.saturating_sub(damage);
+            if pokemon.hp > 0 && pokemon.hp <= max_hp / 2 {
+                pokemon.hp = pokemon.hp.saturating_add(max_hp / 4).min(max_hp);
+                consume_held_item(pokemon);
+            }
+        }
+        _ => pokemon.hp = pokemon.hp.saturating_sub(damage),
+    }
+}
+
+fn consume_held_item(pokemon: &mut Pokemon) {
+    if let Some(held_item) = pokemon.held_item.take() {
+        emit!(HeldItemConsumed {
+            pokemon_mint: pokemon.mint,
+            item_id: held_item.item_id,
+        });
+    }
+}
+
+/// Switch-in trigger: Intimidate lowers the attack of the other side by a stage
+fn on_switch_in(pokemon: &Pokemon, opponent_stat_stages: &mut [i8; 6]) {
+    if pokemon.ability == Ability::Intimidate {
+        opponent_stat_stages[1] = (opponent_stat_stages[1] - 1).max(-MAX_STAT_STAGE);
+        emit_ability(pokemon);
+    }
+}
+
+/// Damage calculation trigger for abilities, held items and weather: Levitate is immune to
+/// Ground moves, Blaze/Torrent/Overgrow boost moves of their type by half below a third of max
+/// HP, a Choice Band boosts every move by half, and sun or rain boost Fire or Water moves by
+/// half while weakening the other type by half
+fn modify_damage(
+    attacker: &Pokemon,
+    defender: &Pokemon,
+    move_type: &PokemonType,
+    weather: &Option<Weather>,
+    damage: u16,
+) -> u16 {
+    if defender.ability == Ability::Levitate && *move_type == PokemonType::Ground {
+        emit_ability(defender);
+        return 0;
+    }
+
+    let mut damage = damage;
+    let pinch_boost = matches!(
+        (&attacker.ability, move_type),
+        (Ability::Blaze, PokemonType::Fire)
+            | (Ability::Torrent, PokemonType::Water)
+            | (Ability::Overgrow, PokemonType::Grass)
+    );
+    if pinch_boost && attacker.hp as u32 * 3 <= max_hp(attacker) as u32 {
+        damage = damage.saturating_mul(3) / 2;
+        emit_ability(attacker);
+    }
+    if held_item_effect(attacker) == Some(HeldItemEffect::ChoiceBand) {
+        damage = damage.saturating_mul(3) / 2;
+    }
+    match (weather, move_type) {
+        (Some(Weather::Sun), PokemonType::Fire) | (Some(Weather::Rain), PokemonType::Water) => {
+            damage.saturating_mul(3) / 2
+        }
+        (Some(Weather::Sun), PokemonType::Water) | (Some(Weather::Rain), PokemonType::Fire) => damage / 2,
+        _ => damage,
+    }
+}
+
+/// Being-hit trigger: Static paralyzes the attacker 30% of the time (`roll` is 0..100)
+fn on_hit(defender: &Pokemon, attacker: &mut Pokemon, roll: u8) {
+    if defender.ability == Ability::Static && roll < 30 && attacker.condition.is_none() {
+        attacker.condition = Some(StatusCondition::Paralysis);
+        emit_ability(defender);
+    }
+}
+
+/// End-of-turn trigger for the Pokemon that just acted: a sandstorm chips a sixteenth of max
+/// HP off anything but Rock, Ground and Steel types, hail off anything but Ice types, then
+/// Leftovers restore a sixteenth. The weather wears off after `WEATHER_TURNS` turns.
+fn end_of_turn(battle: &mut Battle, pokemon: &mut Pokemon) {
+    let max_hp = max_hp(pokemon);
+    let weather_immune: &[PokemonType] = match battle.weather {
+        Some(Weather::Sandstorm) => &[PokemonType::Rock, PokemonType::Ground, PokemonType::Steel],
+        Some(Weather::Hail) => &[PokemonType::Ice],
+        _ => &[],
+    };
+    let chipped = matches!(battle.weather, Some(Weather::Sandstorm) | Some(Weather::Hail));
+    if chipped && !pokemon.types.iter().any(|t| weather_immune.contains(t)) {
+        pokemon.hp = pokemon.hp.saturating_sub((max_hp / 16).max(1));
+    }
+
+    if pokemon.hp > 0 && held_item_effect(pokemon) == Some(HeldItemEffect::Leftovers) {
+        pokemon.hp = pokemon.hp.saturating_add((max_hp / 16).max(1)).min(max_hp);
+    }
+
+    if battle.weather.is_some() {
+        battle.weather_turns = battle.weather_turns.saturating_sub(1);
+        if battle.weather_turns == 0 {
+            battle.weather = None;
+        }
+    }
+}
+
+/// Switch-in damage from the hazards on the Pokemon's side: Stealth Rock takes an eighth of
+/// max HP, Spikes an eighth, sixth or quarter for one to three layers. Flying types and
+/// Levitate avoid Spikes.
+fn apply_entry_hazards(pokemon: &mut Pokemon, hazards: &Hazards) {
+    let max_hp = max_hp(pokemon) as u32;
+    let mut damage = 0;
+    if hazards.stealth_rock {
+        damage += max_hp / 8;
+    }
+    let grounded = pokemon.ability != Ability::Levitate && !pokemon.types.contains(&PokemonType::Flying);
+    if grounded {
+        damage += match hazards.spikes {
+            0 => 0,
+            1 => max_hp / 8,
+            2 => max_hp / 6,
+            _ => max_hp / 4,
+        };
+    }
+    pokemon.hp = pokemon.hp.saturating_sub(damage as u16);
+}
+
+fn emit_ability(pokemon: &Pokemon) {
+    emit!(AbilityActivated {
+        pokemon_mint: pokemon.mint,
+        ability: pokemon.ability.clone(),
+    });
+}
+
+/// Find a slot's hash in the raw SlotHashes sysvar data: a u64 entry count, then
+/// (u64 slot, [u8; 32] hash) entries from newest to oldest
+fn find_slot_hash(data: &[u8], slot: u64) -> Option<[u8; 32]> {
+    let len = u64::from_le_bytes(data.get(..8)?.try_into().ok()?) as usize;
+    for i in 0..len {
+        let entry = data.get(8 + i * 40..8 + (i + 1) * 40)?;
+        let entry_slot = u64::from_le_bytes(entry[..8].try_into().ok()?);
+        if entry_slot == slot {
+            return entry[8..].try_into().ok();
+        }
+        if entry_slot < slot {
+            break;
+        }
+    }
+    None
+}
+
+/// Pick a spawn by weight and a level within its range from an encounter seed
+fn roll_spawn(spawns: &[Spawn], seed: &[u8; 32]) -> (u16, u8) {
+    let species_roll = u64::from_le_bytes(seed[..8].try_into().unwrap());
+    let level_roll = u64::from_le_bytes(seed[8..16].try_into().unwrap());
+
+    let total: u64 = spawns.iter().map(|spawn| spawn.weight as u64).sum();
+    let mut remaining = species_roll % total;
+    let spawn = spawns
+        .iter()
+        .find(|spawn| {
+            if remaining < spawn.weight as u64 {
+                return true;
+            }
+            remaining -= spawn.weight as u64;
+            false
+        })
+        .unwrap_or(&spawns[0]);
+
+    let range = (spawn.max_level - spawn.min_level) as u64 + 1;
+    (spawn.species_id, spawn.min_level + (level_roll % range) as u8)
+}
+
+/// Catch chance out of 256, Gen III style: the species catch rate scaled by the ball bonus
+/// and by how much HP the wild Pokemon has lost (a third of the rate at full HP)
+fn catch_chance(catch_rate: u8, ball_bonus: u16, hp: u16, max_hp: u16) -> u32 {
+    // In u64: an admin-set ball bonus can push the product past u32::MAX
+    let max_hp = max_hp.max(1) as u64;
+    let hp = (hp as u64).min(max_hp);
+    let chance = (3 * max_hp - 2 * hp) * catch_rate as u64 * ball_bonus as u64 / (3 * max_hp * BALL_BONUS_BASE as u64);
+    chance as u32 // at most 255 * u16::MAX / BALL_BONUS_BASE
+}
+
+/// Build the in-memory stats of a program-controlled Pokemon (wild or NPC) so it can go
+/// through the damage formulas
+fn opponent_pokemon(species: &Species, level: u8, hp: u16) -> Pokemon {
+    let mut pokemon = Pokemon {
+        trainer: Pubkey::default(),
+        species_id: species.species_id,
+        name: species.data.name.clone(),
+        nickname: None,
+        level,
+        experience: experience_for_level(level),
+        base_stats: base_stats(&species.data),
+        hp: 0,
+        attack: 0,
+        defense: 0,
+        sp_attack: 0,
+        sp_defense: 0,
+        speed: 0,
+        types: species.data.types.clone(),
+        moves: species.data.moves.clone(),
+        nature: Nature::Hardy,
+        is_shiny: false,
+        status: PokemonStatus::Active,
+        battle: None,
+        condition: None,
+        held_item: None,
+        ability: species.abilities[0].clone(),
+        traded: false,
+        caught_at: 0,
+        mint: Pubkey::default(),
+        bump: 0,
+    };
+    recalculate_stats(&mut pokemon);
+    pokemon.hp = hp;
+    pokemon
+}
+
+fn validate_npc_team(team: &[NpcPokemon]) -> Result<()> {
+    require!(!team.is_empty() && team.len() <= MAX_TEAM_SIZE, ErrorCode::InvalidNpcTeam);
+    for member in team {
+        require!(
+            member.level > 0 && member.level <= MAX_LEVEL && member.moves.len() <= 4,
+            ErrorCode::InvalidNpcTeam
+        );
+    }
+    Ok(())
+}
+
+/// The trainer fights with the team they had when the battle started
+fn start_pve_battle(battle: &mut PveBattle, trainer: &Trainer, level_cap: Option<u8>, bump: u8) -> Result<()> {
+    require!(!trainer.pokemon_team.is_empty(), ErrorCode::EmptyTeam);
+    battle.team = trainer.pokemon_team.clone();
+    battle.fainted = Vec::new();
+    battle.level_cap = level_cap;
+    battle.active_pokemon = None;
+    battle.opponent_index = 0;
+    battle.opponent_hp = None;
+    battle.trainer_stat_stages = [0; 6];
+    battle.opponent_stat_stages = [0; 6];
+    battle.turn = 0;
+    battle.state = BattleState::Active;
+    battle.won = false;
+    battle.started_at = Clock::get()?.unix_timestamp;
+    battle.bump = bump;
+    Ok(())
+}
+
+/// A Pokemon can fight in a PvE battle if it is on the battle's team and still standing
+fn check_pve_pokemon(battle: &PveBattle, pokemon: &Pokemon) -> Result<()> {
+    require!(battle.state == BattleState::Active, ErrorCode::BattleNotActive);
+    require!(pokemon.trainer == battle.trainer, ErrorCode::PokemonNotOwned);
+    require!(
+        battle.team.contains(&pokemon.mint) && !battle.fainted.contains(&pokemon.mint),
+        ErrorCode::PokemonNotAvailable
+    );
+    require!(pokemon.status == PokemonStatus::Active && pokemon.hp > 0, ErrorCode::PokemonNotAvailable);
+    if let Some(level_cap) = battle.level_cap {
+        require!(pokemon.level <= level_cap, ErrorCode::AboveLevelCap);
+    }
+    Ok(())
+}
+
+/// The opponent's current team member; the next one comes in at full HP
+fn pve_opponent(battle: &PveBattle, species: &Species) -> Result<(NpcPokemon, Pokemon)> {
+    let member = battle.opponent_team[battle.opponent_index as usize].clone();
+    require!(species.species_id == member.species_id, ErrorCode::InvalidSpecies);
+
+    let opponent_hp = battle
+        .opponent_hp
+        .unwrap_or_else(|| calculate_stat(species.data.base_hp, member.level, 31));
+    let mut opponent = opponent_pokemon(species, member.level, opponent_hp);
+    if !member.moves.is_empty() {
+        opponent.moves = member.moves.clone();
+    }
+    Ok((member, opponent))
+}
+
+/// Record the active Pokemon fainting after the opponent's move; the battle is lost once the
+/// whole team has fainted
+fn pve_after_opponent_turn(battle: &mut PveBattle, pokemon: &Pokemon) -> Result<()> {
+    if pokemon.hp > 0 || battle.fainted.contains(&pokemon.mint) {
+        return Ok(());
+    }
+    battle.fainted.push(pokemon.mint);
+    if battle.fainted.len() == battle.team.len() {
+        finish_pve_battle(battle, false);
+    }
+    Ok(())
+}
+
+fn finish_pve_battle(battle: &mut PveBattle, won: bool) {
+    battle.state = BattleState::Finished;
+    battle.won = won;
+
+    emit!(PveBattleEnded {
+        trainer: battle.trainer,
+        opponent: battle.opponent.clone(),
+        won,
+        turns: battle.turn,
+    });
+}
+
+/// Whether a trainer's PvE battle account exists and is still being fought
+fn pve_battle_active(pve_battle: &AccountInfo) -> Result<bool> {
+    if pve_battle.data_is_empty() {
+        return Ok(false);
+    }
+    let battle = PveBattle::try_deserialize(&mut &pve_battle.try_borrow_data()?[..])?;
+    Ok(battle.state == BattleState::Active)
+}
+
+/// Load move definitions passed as remaining accounts, one per move in order
+fn load_move_defs<'info>(accounts: &'info [AccountInfo<'info>], moves: &[u16]) -> Result<Vec<MoveDef>> {
+    require!(accounts.len() == moves.len(), ErrorCode::InvalidMoveAccounts);
+    accounts
+        .iter()
+        .zip(moves)
+        .map(|(info, move_id)| {
+            let move_def = Account::<MoveDef>::try_from(info)?;
+            require!(move_def.move_id == *move_id, ErrorCode::InvalidMoveAccounts);
+            Ok(move_def.into_inner())
+        })
+        .collect()
+}
+
+/// Opponent AI policy: the move expected to hit hardest, scored by the damage formula with
+/// immunities applied, then by power and same-type bonus; ties go to the earlier move
+fn choose_ai_move(
+    attacker: &Pokemon,
+    defender: &Pokemon,
+    moves: &[MoveDef],
+    attack_stage: i8,
+    defense_stage: i8,
+) -> Option<usize> {
+    moves
+        .iter()
+        .enumerate()
+        .max_by_key(|(index, move_def)| {
+            let immune = defender.ability == Ability::Levitate && move_def.move_type == PokemonType::Ground;
+            let damage = if move_def.power == 0 || immune {
+                0
+            } else {
+                calculate_damage(attacker, defender, move_def.power, attack_stage, defense_stage)
+            };
+            (
+                damage,
+                move_def.power,
+                attacker.types.contains(&move_def.move_type),
+                Reverse(*index),
+            )
+        })
+        .map(|(index, _)| index)
+}
+
+struct PveExchange {
+    damage: u16,
+    opponent_move_id: Option<u16>,
+    opponent_damage: u16,
+}
+
+/// One PvE turn: the player's Pokemon attacks, then the opponent answers with its AI move if
+/// it is still standing
+fn pve_exchange(
+    pokemon: &mut Pokemon,
+    opponent: &mut Pokemon,
+    move_def: &MoveDef,
+    opponent_moves: &[MoveDef],
+    stat_stages: [i8; 6],
+    opponent_stat_stages: [i8; 6],
+    roll: u8,
+) -> PveExchange {
+    let damage = if move_def.power > 0 {
+        let damage = calculate_damage(pokemon, opponent, move_def.power, stat_stages[1], opponent_stat_stages[2]);
+        modify_damage(pokemon, opponent, &move_def.move_type, &None, damage)
+    } else {
+        0
+    };
+    take_damage(opponent, damage);
+    if damage > 0 {
+        on_hit(opponent, pokemon, roll);
+    }
+
+    let mut exchange = PveExchange {
+        damage,
+        opponent_move_id: None,
+        opponent_damage: 0,
+    };
+    if opponent.hp == 0 {
+        return exchange;
+    }
+
+    (exchange.opponent_move_id, exchange.opponent_damage) =
+        opponent_attack(opponent, pokemon, opponent_moves, opponent_stat_stages, stat_stages);
+    exchange
+}
+
+/// The opponent's AI move against the trainer's Pokemon: the move used and its damage
+fn opponent_attack(
+    opponent: &mut Pokemon,
+    pokemon: &mut Pokemon,
+    opponent_moves: &[MoveDef],
+    opponent_stat_stages: [i8; 6],
+    stat_stages: [i8; 6],
+) -> (Option<u16>, u16) {
+    let Some(index) = choose_ai_move(opponent, pokemon, opponent_moves, opponent_stat_stages[1], stat_stages[2]) else {
+        return (None, 0);
+    };
+    let opponent_move = &opponent_moves[index];
+    let opponent_damage = if opponent_move.power > 0 {
+        let damage = calculate_damage(
+            opponent,
+            pokemon,
+            opponent_move.power,
+            opponent_stat_stages[1],
+            stat_stages[2],
+        );
+        modify_damage(opponent, pokemon, &opponent_move.move_type, &None, damage)
+    } else {
+        0
+    };
+    take_damage(pokemon, opponent_damage);
+    (Some(opponent_move.move_id), opponent_damage)
+}
+
+/// Total experience needed to reach `level` (medium-fast growth rate)
+fn experience_for_level(level: u8) -> u32 {
+    (level as u32).pow(3)
+}
+
+fn defeat_experience(defeated_level: u8) -> u32 {
+    defeated_level as u32 * 50
+}
+
+fn calculate_damage(
+    attacker: &Pokemon,
+    defender: &Pokemon,
+    power: u16,
+    attack_stage: i8,
+    defense_stage: i8,
+) -> u16 {
+    // Simplified damage calculation; scale before dividing so level, power and stat stages
+    // all count
+    let attack_stat = apply_stat_stage(attacker.attack, attack_stage) as u64;
+    let defense_stat = apply_stat_stage(defender.defense, defense_stage).max(1) as u64;
+    let level = attacker.level as u64;
+
+    let damage = (2 * level / 5 + 2) * power as u64 * attack_stat / defense_stat / 50 + 2;
+    damage.min(u16::MAX as u64) as u16
+}
+
+fn pokemon_metadata_name(pokemon: &Pokemon) -> String {
+    let name = pokemon.nickname.as_ref().unwrap_or(&pokemon.name);
+    let shiny = if pokemon.is_shiny { "★ " } else { "" };
+    format!("{}{} #{}", shiny, name, pokemon.species_id)
+}
+
+/// The level is part of the URI so wallets and marketplaces re-fetch the JSON after a level-up
+fn pokemon_metadata_uri(pokemon: &Pokemon, game_state: &GameState) -> String {
+    format!("{}/{}?level={}", game_state.base_uri, pokemon.mint, pokemon.level)
+}
+
+/// Initialize the Token-2022 metadata of a game mint, with update authority the game_state
+/// PDA. Royalty terms are stored as additional fields for marketplaces to honor.
+fn initialize_mint_metadata<'info>(
+    mint: &AccountInfo<'info>,
+    game_state: &Account<'info, GameState>,
+    token_program: &Interface<'info, TokenInterface>,
+    name: String,
+    uri: String,
+) -> Result<()> {
+    let seeds = &[
+        b"game_state".as_ref(),
+        &[game_state.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = TokenMetadataInitialize {
+        token_program_id: token_program.to_account_info(),
+        metadata: mint.clone(),
+        update_authority: game_state.to_account_info(),
+        mint_authority: game_state.to_account_info(),
+        mint: mint.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token_interface::token_metadata_initialize(cpi_ctx, name, "PKMN".to_string(), uri)?;
+
+    update_mint_metadata_field(
+        mint,
+        game_state,
+        token_program,
+        Field::Key("royalty_bps".to_string()),
+        game_state.royalty_bps.to_string(),
+    )?;
+    update_mint_metadata_field(
+        mint,
+        game_state,
+        token_program,
+        Field::Key("royalty_recipient".to_string()),
+        game_state.authority.to_string(),
+    )
+}
+
+fn update_mint_metadata_field<'info>(
+    mint: &AccountInfo<'info>,
+    game_state: &Account<'info, GameState>,
+    token_program: &Interface<'info, TokenInterface>,
+    field: Field,
+    value: String,
+) -> Result<()> {
+    let seeds = &[
+        b"game_state".as_ref(),
+        &[game_state.bump],
+    ];
+    let signer = &[&seeds[..]];
+    let cpi_accounts = TokenMetadataUpdateField {
+        token_program_id: token_program.to_account_info(),
+        metadata: mint.clone(),
+        update_authority: game_state.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token_interface::token_metadata_update_field(cpi_ctx, field, value)
+}
+
+/// Rewrite the name and URI of a Pokemon NFT from its on-chain state. Royalty fields and
+/// collection membership are kept as minted.
+fn refresh_pokemon_metadata<'info>(
+    pokemon: &Pokemon,
+    game_state: &Account<'info, GameState>,
+    pokemon_mint: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    update_mint_metadata_field(pokemon_mint, game_state, token_program, Field::Name, pokemon_metadata_name(pokemon))?;
+    update_mint_metadata_field(pokemon_mint, game_state, token_program, Field::Uri, pokemon_metadata_uri(pokemon, game_state))?;
+    fund_mint_rent(pokemon_mint, payer, system_program)
+}
+
+/// Initialize a Pokemon account at `level` from its species data and mint its Token-2022 NFT
+/// into the trainer's wallet as a member of the game collection
+fn create_pokemon(
+    accounts: &mut CatchPokemon,
+    pokemon_bump: u8,
+    pokemon_data: PokemonData,
+    level: u8,
+    ability_seed: [u8; 32],
+) -> Result<()> {
+    let trainer = &mut accounts.trainer;
+    let species = &accounts.species;
+    require!(species.species_id == pokemon_data.species_id, ErrorCode::InvalidSpecies);
+
+    // Roll one of the species' abilities
+    let roll = u64::from_le_bytes(ability_seed[..8].try_into().unwrap());
+    let ability = species.abilities[(roll % species.abilities.len() as u64) as usize].clone();
+    
+    // Initialize Pokemon NFT
+    let pokemon = &mut accounts.pokemon;
+    pokemon.trainer = trainer.public_key;
+    pokemon.species_id = pokemon_data.species_id;
+    pokemon.name = pokemon_data.name.clone();
+    pokemon.nickname = None;
+    pokemon.level = level;
+    pokemon.experience = experience_for_level(pokemon.level);
+    pokemon.base_stats = base_stats(&pokemon_data);
+    recalculate_stats(pokemon);
+    pokemon.types = pokemon_data.types;
+    pokemon.moves = pokemon_data.moves;
+    pokemon.nature = Nature::Hardy; // Default nature
+    pokemon.is_shiny = false; // TODO: Add shiny probability
+    pokemon.status = PokemonStatus::Active;
+    pokemon.battle = None;
+    pokemon.condition = None;
+    pokemon.held_item = None;
+    pokemon.ability = ability;
+    pokemon.traded = false;
+    pokemon.caught_at = Clock::get()?.unix_timestamp;
+    pokemon.mint = accounts.pokemon_mint.key();
+    pokemon.bump = pokemon_bump;
+
+    // Mint the NFT
+    let game_state = &accounts.game_state;
+    let seeds = &[
+        b"game_state".as_ref(),
+        &[game_state.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = MintTo {
+        mint: accounts.pokemon_mint.to_account_info(),
+        to: accounts.trainer_pokemon_account.to_account_info(),
+        authority: game_state.to_account_info(),
+    };
+    let cpi_program = accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+    token_interface::mint_to(cpi_ctx, 1)?;
+
+    // Token-2022 metadata lives in the Pokemon mint itself (metadata pointer)
+    initialize_mint_metadata(
+        &accounts.pokemon_mint.to_account_info(),
+        game_state,
+        &accounts.token_program,
+        pokemon_metadata_name(pokemon),
+        pokemon_metadata_uri(pokemon, game_state),
+    )?;
+
+    // Join the game collection (token group)
+    let cpi_accounts = TokenMemberInitialize {
+        token_program_id: accounts.token_program.to_account_info(),
+        member: accounts.pokemon_mint.to_account_info(),
+        member_mint: accounts.pokemon_mint.to_account_info(),
+        member_mint_authority: game_state.to_account_info(),
+        group: accounts.collection_mint.to_account_info(),
+        group_update_authority: game_state.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(accounts.token_program.to_account_info(), cpi_accounts, signer);
+    token_interface::token_member_initialize(cpi_ctx)?;
+
+    // Revoke the mint authority so no second copy of this Pokemon can ever be minted
+    revoke_mint_authority(
+        &accounts.pokemon_mint.to_account_info(),
+        game_state,
+        &accounts.token_program,
+    )?;
+    fund_mint_rent(
+        &accounts.pokemon_mint.to_account_info(),
+        &accounts.payer.to_account_info(),
+        &accounts.system_program,
+    )?;
+
+    // Add to trainer's collection
+    add_to_collection(trainer, accounts.pokemon_mint.key())?;
+
+    trainer.pokemon_caught += 1;
+    accounts.game_state.total_pokemon += 1;
+
+    emit!(PokemonCaught {
+        trainer: trainer.public_key,
+        pokemon_mint: accounts.pokemon_mint.key(),
+        species_id: pokemon_data.species_id,
+        name: pokemon_data.name,
+    });
+
+    Ok(())
+}
+
+/// Leaf metadata of a compressed Pokemon. Its species attributes are spelled out in the
+/// URI so the leaf hash commits to them and decompression can check the claimed data.
+fn compressed_pokemon_metadata(data: &PokemonData, config: &CompressedMintConfig) -> MetadataArgs {
+    let stats = base_stats(data).map(|stat| stat.to_string()).join(",");
+    let types = data.types.iter().map(|t| (t.clone() as u8).to_string()).collect::<Vec<_>>().join(",");
+    let moves = data.moves.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(",");
+
+    MetadataArgs {
+        name: format!("{} #{}", data.name, data.species_id),
+        symbol: "PKMN".to_string(),
+        uri: format!(
+            "{}/compressed/{}?stats={}&types={}&moves={}",
+            config.base_uri, data.species_id, stats, types, moves
+        ),
+        seller_fee_basis_points: config.royalty_bps,
+        primary_sale_happened: false,
+        is_mutable: false,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: None,
+        uses: None,
+        token_program_version: TokenProgramVersion::Original,
+        creators: vec![LeafCreator {
+            address: config.royalty_recipient,
+            verified: false,
+            share: 100,
+        }],
+    }
+}
+
+/// Drop the mint authority of a game NFT so its supply stays at one
+fn revoke_mint_authority<'info>(
+    mint: &AccountInfo<'info>,
+    game_state: &Account<'info, GameState>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let seeds = &[
+        b"game_state".as_ref(),
+        &[game_state.bump],
+    ];
+    let signer = &[&seeds[..]];
+    let cpi_accounts = SetAuthority {
+        current_authority: game_state.to_account_info(),
+        account_or_mint: mint.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token_interface::set_authority(cpi_ctx, AuthorityType::MintTokens, None)
+}
+
+/// Token-2022 reallocates a mint when its metadata or group member data grows; top it back
+/// up to rent exemption from the payer
+fn fund_mint_rent<'info>(
+    mint: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let missing = Rent::get()?
+        .minimum_balance(mint.data_len())
+        .saturating_sub(mint.lamports());
+    if missing > 0 {
+        let cpi_accounts = system_program::Transfer {
+            from: payer.clone(),
+            to: mint.clone(),
+        };
+        system_program::transfer(CpiContext::new(system_program.to_account_info(), cpi_accounts), missing)?;
+    }
+    Ok(())
+}
+
+/// Worth of a Pokemon in whole PokeCoins, paid out when it is released and earned daily while
+/// staked: grows with its level and the rarity of its species (base stat total), and shinies
+/// are worth five times as much
+fn pokemon_value(pokemon: &Pokemon) -> u64 {
+    let base_stat_total: u64 = pokemon.base_stats.iter().map(|&stat| stat as u64).sum();
+    let reward = pokemon.level as u64 * base_stat_total / 100;
+    if pokemon.is_shiny {
+        reward * SHINY_VALUE_MULTIPLIER
+    } else {
+        reward
+    }
+}
+
+/// Whole PokeCoins a battle win is worth before the season budget applies: scales with the
+/// defeated trainer's level, with a bonus for each level of the winner
+fn battle_reward(winner: &Trainer, loser: &Trainer) -> u64 {
+    let reward = BATTLE_REWARD_PER_LEVEL * loser.level.max(1) as u64;
+    reward * (100 + RANK_BONUS_PERCENT * winner.level as u64) / 100
+}
+
+/// Pay the PokeCoin a staked Pokemon earned since the last claim out of the treasury. The
+/// payout is capped by the treasury balance; whatever it cannot cover is forfeited.
+fn pay_staking_rewards<'info>(
+    stake: &mut Account<'info, Stake>,
+    pokemon: &Pokemon,
+    game_state: &Account<'info, GameState>,
+    treasury: &InterfaceAccount<'info, TokenAccount>,
+    destination: &InterfaceAccount<'info, TokenAccount>,
+    poke_coin_mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let slot = Clock::get()?.slot;
+    let elapsed = slot.saturating_sub(stake.last_claim_slot);
+    let earned = (pokemon_value(pokemon) as u128)
+        .checked_mul(elapsed as u128)
+        .and_then(|units| units.checked_mul(10u128.pow(poke_coin_mint.decimals as u32)))
+        .ok_or(ErrorCode::MathOverflow)?
+        / SLOTS_PER_DAY as u128;
+    let reward = u64::try_from(earned).unwrap_or(u64::MAX).min(treasury.amount);
+    stake.last_claim_slot = slot;
+
+    if reward > 0 {
+        let seeds = &[
+            b"game_state".as_ref(),
+            &[game_state.bump],
+        ];
+        pda_transfer(
+            treasury,
+            destination,
+            poke_coin_mint,
+            &game_state.to_account_info(),
+            &[&seeds[..]],
+            reward,
+            token_program,
+        )?;
+    }
+
+    emit!(StakingRewardsClaimed {
+        owner: stake.owner,
+        pokemon_mint: stake.pokemon_mint,
+        reward,
+    });
+
+    Ok(())
+}
+
+fn protocol_fee(amount: u64, fee_bps: u16) -> u64 {
+    (amount as u128 * fee_bps as u128 / 10_000) as u64
+}
+
+fn add_to_collection(trainer: &mut Trainer, pokemon_mint: Pubkey) -> Result<()> {
+    if trainer.pokemon_team.len() < 6 {
+        trainer.pokemon_team.push(pokemon_mint);
+    } else {
+        require!(trainer.pokemon_box.len() < MAX_BOX_SIZE, ErrorCode::PokemonBoxFull);
+        trainer.pokemon_box.push(pokemon_mint);
+    }
+    Ok(())
+}
+
+fn remove_from_collection(trainer: &mut Trainer, pokemon_mint: &Pubkey) {
+    trainer.pokemon_team.retain(|mint| mint != pokemon_mint);
+    trainer.pokemon_box.retain(|mint| mint != pokemon_mint);
+}
+
+fn add_to_inventory(inventory: &mut Inventory, item_id: u16, quantity: u32) -> Result<()> {
+    match inventory.items.iter_mut().find(|stack| stack.item_id == item_id) {
+        Some(stack) => {
+            stack.quantity = stack.quantity.checked_add(quantity).ok_or(ErrorCode::MathOverflow)?;
+        }
+        None => {
+            require!(inventory.items.len() < MAX_INVENTORY_SLOTS, ErrorCode::InventoryFull);
+            inventory.items.push(ItemStack { item_id, quantity });
+        }
+    }
+    Ok(())
+}
+
+fn remove_from_inventory(inventory: &mut Inventory, item_id: u16, quantity: u32) -> Result<()> {
+    let stack = inventory
+        .items
+        .iter_mut()
+        .find(|stack| stack.item_id == item_id)
+        .ok_or(ErrorCode::NotEnoughItems)?;
+    stack.quantity = stack.quantity.checked_sub(quantity).ok_or(ErrorCode::NotEnoughItems)?;
+    inventory.items.retain(|stack| stack.quantity > 0);
+    Ok(())
+}
+
+/// Send the escrowed NFT to `destination` and close the escrow, refunding rent to the seller
+fn release_listing_escrow<'info>(
+    listing: &Account<'info, Listing>,
+    escrow: &InterfaceAccount<'info, TokenAccount>,
+    destination: &InterfaceAccount<'info, TokenAccount>,
+    pokemon_mint: &InterfaceAccount<'info, Mint>,
+    seller: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let seeds = &[
+        b"listing".as_ref(),
+        listing.pokemon_mint.as_ref(),
+        &[listing.bump],
+    ];
+    let signer = &[&seeds[..]];
+    let authority = listing.to_account_info();
+
+    pda_transfer(escrow, destination, pokemon_mint, &authority, signer, 1, token_program)?;
+    pda_close(escrow, seller, &authority, signer, token_program)
+}
+
+/// Transfer tokens out of an account owned by a program PDA
+fn pda_transfer<'info>(
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+    amount: u64,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let cpi_accounts = TransferChecked {
+        from: from.to_account_info(),
+        mint: mint.to_account_info(),
+        to: to.to_account_info(),
+        authority: authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)
+}
+
+/// Close an empty token account owned by a program PDA
+fn pda_close<'info>(
+    account: &InterfaceAccount<'info, TokenAccount>,
+    destination: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let cpi_accounts = CloseAccount {
+        account: account.to_account_info(),
+        destination: destination.clone(),
+        authority: authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token_interface::close_account(cpi_ctx)
+}
+
+/// Close an emptied PokeCoin vault. Fees withheld from transfers into it would block the
+/// close, so they are harvested to the mint first.
+fn close_coin_vault<'info>(
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    destination: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let vault_info = vault.to_account_info();
+    let withheld = {
+        let data = vault_info.try_borrow_data()?;
+        let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?;
+        state
+            .get_extension::<TransferFeeAmount>()
+            .map_or(0, |fee| u64::from(fee.withheld_amount))
+    };
+    if withheld > 0 {
+        let cpi_accounts = HarvestWithheldTokensToMint {
+            token_program_id: token_program.to_account_info(),
+            mint: mint.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+        harvest_withheld_tokens_to_mint(cpi_ctx, vec![vault_info])?;
+    }
+    pda_close(vault, destination, authority, signer, token_program)
+}
+
+fn has_unique_mints(mints: &[Pubkey]) -> bool {
+    mints.iter().enumerate().all(|(i, mint)| !mints[..i].contains(mint))
+}
+
+/// Load a Pokemon passed through remaining accounts and check it matches `expected_mint`
+fn load_trade_pokemon<'info>(
+    pokemon_info: &'info AccountInfo<'info>,
+    mint_info: &AccountInfo<'info>,
+    expected_mint: &Pubkey,
+) -> Result<Account<'info, Pokemon>> {
+    require!(pokemon_info.is_writable, ErrorCode::InvalidTradeAccounts);
+    let pokemon = Account::<Pokemon>::try_from(pokemon_info)?;
+    require!(
+        pokemon.mint == *expected_mint && mint_info.key() == *expected_mint,
+        ErrorCode::InvalidPokemonMint
+    );
+    Ok(pokemon)
+}
+
+fn create_ata_if_needed<'info>(
+    payer: &AccountInfo<'info>,
+    associated_token: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    token_program: &Interface<'info, TokenInterface>,
+    associated_token_program: &Program<'info, AssociatedToken>,
+) -> Result<()> {
+    let cpi_accounts = associated_token::Create {
+        payer: payer.clone(),
+        associated_token: associated_token.clone(),
+        authority: authority.clone(),
+        mint: mint.clone(),
+        system_program: system_program.to_account_info(),
+        token_program: token_program.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(associated_token_program.to_account_info(), cpi_accounts);
+    associated_token::create_idempotent(cpi_ctx)
+}
+
+/// Return every escrowed Pokemon and PokeCoin of a trade to the proposer
+fn return_trade_escrow<'info>(ctx: &Context<'_, '_, 'info, 'info, CloseTrade<'info>>) -> Result<()> {
+    let trade = &ctx.accounts.trade;
+    require!(
+        ctx.remaining_accounts.len() == trade.offered_mints.len() * 4,
+        ErrorCode::InvalidTradeAccounts
+    );
+
+    let seeds = &[
+        b"trade".as_ref(),
+        trade.proposer.as_ref(),
+        trade.recipient.as_ref(),
+        &[trade.bump],
+    ];
+    let signer = &[&seeds[..]];
+    let trade_info = trade.to_account_info();
+    let proposer_info = ctx.accounts.proposer.to_account_info();
+
+    for (accounts, mint) in ctx.remaining_accounts.chunks(4).zip(trade.offered_mints.iter()) {
+        let (pokemon_info, mint_info, escrow, to) = (&accounts[0], &accounts[1], &accounts[2], &accounts[3]);
+
+        let mut pokemon = load_trade_pokemon(pokemon_info, mint_info, mint)?;
+        pokemon.status = PokemonStatus::Active;
+        pokemon.exit(&crate::ID)?;
+
+        // Escrowed Pokemon only ever go back to the proposer's own token account
+        let proposer_ata = associated_token::get_associated_token_address_with_program_id(
+            &trade.proposer,
+            mint,
+            &ctx.accounts.token_program.key(),
+        );
+        require!(to.key() == proposer_ata, ErrorCode::InvalidTradeAccounts);
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_accounts = TransferChecked {
+            from: escrow.clone(),
+            mint: mint_info.clone(),
+            to: to.clone(),
+            authority: trade_info.clone(),
+        };
+        token_interface::transfer_checked(CpiContext::new_with_signer(cpi_program.clone(), cpi_accounts, signer), 1, 0)?;
+        let cpi_accounts = CloseAccount {
+            account: escrow.clone(),
+            destination: proposer_info.clone(),
+            authority: trade_info.clone(),
+        };
+        token_interface::close_account(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer))?;
+    }
+
+    let escrowed_coins = ctx.accounts.coin_vault.amount;
+    if escrowed_coins > 0 {
+        pda_transfer(
+            &ctx.accounts.coin_vault,
+            &ctx.accounts.proposer_coin_account,
+            &ctx.accounts.poke_coin_mint,
+            &trade_info,
+            signer,
+            escrowed_coins,
+            &ctx.accounts.coin_token_program,
+        )?;
+    }
+    close_coin_vault(
+        &ctx.accounts.coin_vault,
+        &ctx.accounts.poke_coin_mint,
+        &proposer_info,
+        &trade_info,
+        signer,
+        &ctx.accounts.coin_token_program,
+    )?;
+
+    emit!(TradeClosed {
+        proposer: trade.proposer,
+        recipient: trade.recipient,
+        closed_by: ctx.accounts.authority.key(),
+    });
+
+    Ok(())
+}
+
+fn end_battle(battle: &mut Battle, winner: u8) -> Result<()> {
+    battle.state = BattleState::Finished;
+    battle.winner = Some(winner);
+    battle.ended_at = Some(Clock::get()?.unix_timestamp);
+
+    emit!(BattleEnded {
+        battle_id: battle.id,
+        winner,
+    });
+
+    Ok(())
+}
+
+/// The side holding up an active battle: one that hasn't sent out a Pokemon yet, otherwise
+/// the one whose turn it is (0 = challenger, 1 = opponent)
+fn stalling_side(battle: &Battle) -> u8 {
+    match (battle.challenger_active_pokemon, battle.opponent_active_pokemon) {
+        (None, Some(_)) => 0,
+        (Some(_), None) => 1,
+        _ => (battle.current_turn % 2) as u8,
+    }
+}
+
+/// A Pokemon sent out in a PvP battle can't leave the trainer until that battle is over. The
+/// battle's account must be passed while the Pokemon is tied to it; once it has finished or
+/// been settled the tie is cleared.
+fn check_not_in_battle(pokemon: &mut Pokemon, battle: Option<&AccountInfo>) -> Result<()> {
+    let Some(battle_key) = pokemon.battle else {
+        return Ok(());
+    };
+    let battle = battle
+        .filter(|info| info.key() == battle_key)
+        .ok_or(ErrorCode::PokemonInBattle)?;
+    if !battle.data_is_empty() {
+        let battle = Battle::try_deserialize(&mut &battle.try_borrow_data()?[..])?;
+        require!(battle.state == BattleState::Finished, ErrorCode::PokemonInBattle);
+    }
+    pokemon.battle = None;
+    Ok(())
+}
+
+// Account structures and data types follow...
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + GameState::INIT_SPACE,
+        seeds = [b"game_state"],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mint::token_program = coin_token_program)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Treasury account
+    pub treasury: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = 0,
+        mint::authority = game_state,
+        mint::freeze_authority = game_state,
+        mint::token_program = token_program,
+        extensions::metadata_pointer::authority = game_state,
+        extensions::metadata_pointer::metadata_address = collection_mint,
+        extensions::group_pointer::authority = game_state,
+        extensions::group_pointer::group_address = collection_mint,
+    )]
+    pub collection_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = collection_mint,
+        associated_token::authority = game_state,
+        associated_token::token_program = token_program,
+    )]
+    pub collection_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// PokeCoin may live under SPL Token or Token-2022; Pokemon NFTs need Token-2022 extensions
+    pub coin_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterTrainer<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Trainer::INIT_SPACE,
+        seeds = [b"trainer", authority.key().as_ref()],
+        bump
+    )]
+    pub trainer: Account<'info, Trainer>,
+    #[account(mut)]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = poke_coin_mint,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program,
+    )]
+    pub trainer_coin_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CatchPokemon<'info> {
+    #[account(
+        mut,
+        seeds = [b"trainer", trainer_authority.key().as_ref()],
+        bump = trainer.bump
+    )]
+    pub trainer: Account<'info, Trainer>,
+    #[account(mut)]
+    pub game_state: Account<'info, GameState>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Pokemon::INIT_SPACE,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump
+    )]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 0,
+        mint::authority = game_state,
+        mint::token_program = token_program,
+        extensions::metadata_pointer::authority = game_state,
+        extensions::metadata_pointer::metadata_address = pokemon_mint,
+        extensions::group_member_pointer::authority = game_state,
+        extensions::group_member_pointer::member_address = pokemon_mint,
+        extensions::close_authority::authority = game_state,
+    )]
+    pub pokemon_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = trainer_authority,
+        associated_token::token_program = token_program,
+    )]
+    pub trainer_pokemon_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = game_state.collection_mint)]
+    pub collection_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        seeds = [b"species", species.species_id.to_le_bytes().as_ref()],
+        bump = species.bump
+    )]
+    pub species: Box<Account<'info, Species>>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub trainer_authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreatePokemonTree<'info> {
+    #[account(mut, has_one = authority)]
+    pub game_state: Account<'info, GameState>,
+    /// CHECK: Bubblegum tree config PDA, initialized by Bubblegum
+    #[account(mut)]
+    pub tree_config: AccountInfo<'info>,
+    /// CHECK: Pre-allocated concurrent Merkle tree, initialized by the compression program
+    #[account(mut)]
+    pub merkle_tree: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: SPL Noop program
+    #[account(address = SPL_NOOP_ID)]
+    pub log_wrapper: AccountInfo<'info>,
+    /// CHECK: SPL Account Compression program
+    #[account(address = SPL_ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: AccountInfo<'info>,
+    /// CHECK: Bubblegum program
+    #[account(address = mpl_bubblegum::ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintCompressedPokemon<'info> {
+    #[account(has_one = authority)]
+    pub game_state: Account<'info, GameState>,
+    /// CHECK: Bubblegum tree config PDA, checked by Bubblegum
+    #[account(mut)]
+    pub tree_config: AccountInfo<'info>,
+    /// CHECK: The game's Merkle tree
+    #[account(mut, address = game_state.pokemon_tree)]
+    pub merkle_tree: AccountInfo<'info>,
+    /// CHECK: Wallet receiving the compressed Pokemon
+    pub leaf_owner: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: SPL Noop program
+    #[account(address = SPL_NOOP_ID)]
+    pub log_wrapper: AccountInfo<'info>,
+    /// CHECK: SPL Account Compression program
+    #[account(address = SPL_ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: AccountInfo<'info>,
+    /// CHECK: Bubblegum program
+    #[account(address = mpl_bubblegum::ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DecompressPokemon<'info> {
+    pub catch: CatchPokemon<'info>,
+    /// CHECK: Bubblegum tree config PDA, checked by Bubblegum
+    #[account(mut)]
+    pub tree_config: AccountInfo<'info>,
+    /// CHECK: The game's Merkle tree
+    #[account(mut, address = catch.game_state.pokemon_tree)]
+    pub merkle_tree: AccountInfo<'info>,
+    /// CHECK: SPL Noop program
+    #[account(address = SPL_NOOP_ID)]
+    pub log_wrapper: AccountInfo<'info>,
+    /// CHECK: SPL Account Compression program
+    #[account(address = SPL_ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: AccountInfo<'info>,
+    /// CHECK: Bubblegum program
+    #[account(address = mpl_bubblegum::ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePokemon<'info> {
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    pub game_state: Account<'info, GameState>,
+    #[account(mut, address = pokemon.mint)]
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    pub trainer_authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EvolvePokemon<'info> {
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(has_one = authority)]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut, address = pokemon.mint)]
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    pub trainer_authority: Signer<'info>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TradeEvolve<'info> {
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(
+        seeds = [b"species", pokemon.species_id.to_le_bytes().as_ref()],
+        bump = species.bump
+    )]
+    pub species: Box<Account<'info, Species>>,
+    #[account(
+        seeds = [b"species", evolution.species_id.to_le_bytes().as_ref()],
+        bump = evolution.bump
+    )]
+    pub evolution: Box<Account<'info, Species>>,
+    pub game_state: Account<'info, GameState>,
+    #[account(mut, address = pokemon.mint)]
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    pub trainer_authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MakeShiny<'info> {
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(has_one = authority)]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut, address = pokemon.mint)]
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshMetadata<'info> {
+    pub pokemon: Account<'info, Pokemon>,
+    pub game_state: Account<'info, GameState>,
+    #[account(mut, address = pokemon.mint)]
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Additional account structs for other instructions...
+#[derive(Accounts)]
+pub struct ReleasePokemon<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump = pokemon.bump
+    )]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(mut)]
+    pub pokemon_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_pokemon_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"trainer", owner.key().as_ref()],
+        bump = trainer.bump
+    )]
+    pub trainer: Account<'info, Trainer>,
+    pub game_state: Account<'info, GameState>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        address = game_state.treasury,
+        token::authority = game_state,
+        token::token_program = coin_token_program,
+    )]
+    pub treasury_coin_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = game_state.poke_coin_mint,
+        token::authority = owner,
+        token::token_program = coin_token_program,
+    )]
+    pub owner_coin_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: The PvP battle the Pokemon was last sent out in, required until that battle is over
+    pub battle: Option<AccountInfo<'info>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub coin_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct StakePokemon<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Stake::INIT_SPACE,
+        seeds = [b"stake", pokemon_mint.key().as_ref()],
+        bump
+    )]
+    pub stake: Account<'info, Stake>,
+    #[account(
+        mut,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump = pokemon.bump
+    )]
+    pub pokemon: Account<'info, Pokemon>,
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = stake,
+        associated_token::token_program = token_program,
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: The PvP battle the Pokemon was last sent out in, required until that battle is over
+    pub battle: Option<AccountInfo<'info>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimStakingRewards<'info> {
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"stake", stake.pokemon_mint.as_ref()],
+        bump = stake.bump
+    )]
+    pub stake: Account<'info, Stake>,
+    #[account(
+        seeds = [b"pokemon", stake.pokemon_mint.as_ref()],
+        bump = pokemon.bump
+    )]
+    pub pokemon: Account<'info, Pokemon>,
+    pub game_state: Account<'info, GameState>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        address = game_state.treasury,
+        token::authority = game_state,
+        token::token_program = coin_token_program,
+    )]
+    pub treasury_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = poke_coin_mint,
+        token::authority = owner,
+        token::token_program = coin_token_program,
+    )]
+    pub owner_coin_account: InterfaceAccount<'info, TokenAccount>,
+    pub owner: Signer<'info>,
+    pub coin_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakePokemon<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"stake", pokemon_mint.key().as_ref()],
+        bump = stake.bump
+    )]
+    pub stake: Box<Account<'info, Stake>>,
+    #[account(
+        mut,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump = pokemon.bump
+    )]
+    pub pokemon: Box<Account<'info, Pokemon>>,
+    pub pokemon_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = stake,
+        associated_token::token_program = token_program,
+    )]
+    pub stake_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_pokemon_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub game_state: Box<Account<'info, GameState>>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        address = game_state.treasury,
+        token::authority = game_state,
+        token::token_program = coin_token_program,
+    )]
+    pub treasury_coin_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = poke_coin_mint,
+        token::authority = owner,
+        token::token_program = coin_token_program,
+    )]
+    pub owner_coin_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub coin_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct OfferRental<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Rental::INIT_SPACE,
+        seeds = [b"rental", pokemon_mint.key().as_ref()],
+        bump
+    )]
+    pub rental: Account<'info, Rental>,
+    #[account(
+        mut,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump = pokemon.bump
+    )]
+    pub pokemon: Account<'info, Pokemon>,
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = rental,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: The PvP battle the Pokemon was last sent out in, required until that battle is over
+    pub battle: Option<AccountInfo<'info>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RentPokemon<'info> {
+    #[account(
+        mut,
+        seeds = [b"rental", rental.pokemon_mint.as_ref()],
+        bump = rental.bump
+    )]
+    pub rental: Account<'info, Rental>,
+    pub game_state: Account<'info, GameState>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = poke_coin_mint,
+        token::authority = borrower,
+        token::token_program = token_program,
+    )]
+    pub borrower_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = poke_coin_mint,
+        token::authority = rental.owner,
+        token::token_program = token_program,
+    )]
+    pub owner_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, address = game_state.treasury)]
+    pub treasury_coin_account: InterfaceAccount<'info, TokenAccount>,
+    pub borrower: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct EndRental<'info> {
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        seeds = [b"rental", pokemon_mint.key().as_ref()],
+        bump = rental.bump
+    )]
+    pub rental: Account<'info, Rental>,
+    #[account(
+        mut,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump = pokemon.bump
+    )]
+    pub pokemon: Account<'info, Pokemon>,
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = rental,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(data: PokemonData)]
+pub struct RegisterSpecies<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Species::INIT_SPACE,
+        seeds = [b"species", data.species_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub species: Account<'info, Species>,
+    #[account(has_one = authority)]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(move_id: u16)]
+pub struct RegisterMove<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + MoveDef::INIT_SPACE,
+        seeds = [b"move", move_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub move_def: Account<'info, MoveDef>,
+    #[account(has_one = authority)]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(zone_id: u16)]
+pub struct CreateZone<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + SpawnZone::INIT_SPACE,
+        seeds = [b"zone", zone_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub zone: Account<'info, SpawnZone>,
+    #[account(has_one = authority)]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(zone_id: u16)]
+pub struct EncounterWild<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Encounter::INIT_SPACE,
+        seeds = [b"encounter", owner.key().as_ref()],
+        bump
+    )]
+    pub encounter: Account<'info, Encounter>,
+    #[account(
+        seeds = [b"zone", zone_id.to_le_bytes().as_ref()],
+        bump = zone.bump
+    )]
+    pub zone: Account<'info, SpawnZone>,
+    #[account(
+        mut,
+        seeds = [b"trainer", owner.key().as_ref()],
+        bump = trainer.bump
+    )]
+    pub trainer: Account<'info, Trainer>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealEncounter<'info> {
+    #[account(
+        mut,
+        seeds = [b"encounter", owner.key().as_ref()],
+        bump = encounter.bump
+    )]
+    pub encounter: Account<'info, Encounter>,
+    #[account(
+        seeds = [b"zone", encounter.zone_id.to_le_bytes().as_ref()],
+        bump = zone.bump
+    )]
+    pub zone: Account<'info, SpawnZone>,
+    #[account(
+        seeds = [b"species", species.species_id.to_le_bytes().as_ref()],
+        bump = species.bump
+    )]
+    pub species: Account<'info, Species>,
+    /// CHECK: SlotHashes sysvar, read by hand
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(move_id: u16)]
+pub struct AttackWild<'info> {
+    #[account(
+        mut,
+        seeds = [b"encounter", owner.key().as_ref()],
+        bump = encounter.bump
+    )]
+    pub encounter: Account<'info, Encounter>,
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(
+        constraint = Some(species.species_id) == encounter.species_id @ ErrorCode::InvalidSpecies,
+        seeds = [b"species", species.species_id.to_le_bytes().as_ref()],
+        bump = species.bump
+    )]
+    pub species: Account<'info, Species>,
+    #[account(
+        seeds = [b"move", move_id.to_le_bytes().as_ref()],
+        bump = move_def.bump
+    )]
+    pub move_def: Account<'info, MoveDef>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(npc_id: u16)]
+pub struct CreateNpcTrainer<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + NpcTrainer::INIT_SPACE,
+        seeds = [b"npc", npc_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub npc: Account<'info, NpcTrainer>,
+    #[account(has_one = authority)]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(npc_id: u16)]
+pub struct StartNpcBattle<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PveBattle::INIT_SPACE,
+        seeds = [b"pve_battle", owner.key().as_ref()],
+        bump
+    )]
+    pub pve_battle: Account<'info, PveBattle>,
+    #[account(
+        seeds = [b"npc", npc_id.to_le_bytes().as_ref()],
+        bump = npc.bump
+    )]
+    pub npc: Account<'info, NpcTrainer>,
+    #[account(
+        seeds = [b"trainer", owner.key().as_ref()],
+        bump = trainer.bump
+    )]
+    pub trainer: Account<'info, Trainer>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(gym_id: u16, leader_team: Vec<NpcPokemon>, level_cap: u8, type_specialty: PokemonType, badge_id: u8)]
+pub struct CreateGym<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Gym::INIT_SPACE,
+        seeds = [b"gym", gym_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub gym: Account<'info, Gym>,
+    /// Claims the badge ID, so no two gyms can award the same badge
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Badge::INIT_SPACE,
+        seeds = [b"badge", [badge_id].as_ref()],
+        bump
+    )]
+    pub badge: Account<'info, Badge>,
+    #[account(has_one = authority)]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(gym_id: u16)]
+pub struct ChallengeGym<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PveBattle::INIT_SPACE,
+        seeds = [b"pve_battle", owner.key().as_ref()],
+        bump
+    )]
+    pub pve_battle: Account<'info, PveBattle>,
+    #[account(
+        seeds = [b"gym", gym_id.to_le_bytes().as_ref()],
+        bump = gym.bump
+    )]
+    pub gym: Account<'info, Gym>,
+    #[account(
+        seeds = [b"trainer", owner.key().as_ref()],
+        bump = trainer.bump
+    )]
+    pub trainer: Account<'info, Trainer>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimGymBadge<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"pve_battle", owner.key().as_ref()],
+        bump = pve_battle.bump
+    )]
+    pub pve_battle: Account<'info, PveBattle>,
+    #[account(
+        seeds = [b"gym", gym.gym_id.to_le_bytes().as_ref()],
+        bump = gym.bump
+    )]
+    pub gym: Account<'info, Gym>,
+    #[account(
+        mut,
+        seeds = [b"trainer", owner.key().as_ref()],
+        bump = trainer.bump
+    )]
+    pub trainer: Account<'info, Trainer>,
+    #[account(mut)]
+    pub game_state: Account<'info, GameState>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        address = game_state.treasury,
+        token::authority = game_state,
+        token::token_program = token_program,
+    )]
+    pub treasury_coin_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = game_state.poke_coin_mint,
+        token::authority = owner,
+        token::token_program = token_program,
+    )]
+    pub owner_coin_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(move_id: u16)]
+pub struct PveMove<'info> {
+    #[account(
+        mut,
+        seeds = [b"pve_battle", owner.key().as_ref()],
+        bump = pve_battle.bump
+    )]
+    pub pve_battle: Account<'info, PveBattle>,
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(
+        seeds = [b"species", opponent_species.species_id.to_le_bytes().as_ref()],
+        bump = opponent_species.bump
+    )]
+    pub opponent_species: Account<'info, Species>,
+    #[account(
+        seeds = [b"move", move_id.to_le_bytes().as_ref()],
+        bump = move_def.bump
+    )]
+    pub move_def: Account<'info, MoveDef>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PveSwitch<'info> {
+    #[account(
+        mut,
+        seeds = [b"pve_battle", owner.key().as_ref()],
+        bump = pve_battle.bump
+    )]
+    pub pve_battle: Account<'info, PveBattle>,
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(
+        seeds = [b"species", opponent_species.species_id.to_le_bytes().as_ref()],
+        bump = opponent_species.bump
+    )]
+    pub opponent_species: Account<'info, Species>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EndPveBattle<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"pve_battle", owner.key().as_ref()],
+        bump = pve_battle.bump
+    )]
+    pub pve_battle: Account<'info, PveBattle>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ThrowBall<'info> {
+    #[account(
+        mut,
+        seeds = [b"encounter", owner.key().as_ref()],
+        bump = encounter.bump
+    )]
+    pub encounter: Account<'info, Encounter>,
+    #[account(
+        constraint = Some(species.species_id) == encounter.species_id @ ErrorCode::InvalidSpecies,
+        seeds = [b"species", species.species_id.to_le_bytes().as_ref()],
+        bump = species.bump
+    )]
+    pub species: Account<'info, Species>,
+    #[account(
+        seeds = [b"item", item.id.to_le_bytes().as_ref()],
+        bump = item.bump
+    )]
+    pub item: Account<'info, Item>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"inventory", owner.key().as_ref()],
+        bump = inventory.bump
+    )]
+    pub inventory: Account<'info, Inventory>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealThrow<'info> {
+    #[account(
+        mut,
+        seeds = [b"encounter", owner.key().as_ref()],
+        bump = encounter.bump
+    )]
+    pub encounter: Account<'info, Encounter>,
+    /// CHECK: SlotHashes sysvar, read by hand
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWildPokemon<'info> {
+    pub catch: CatchPokemon<'info>,
+    #[account(
+        mut,
+        seeds = [b"encounter", catch.trainer_authority.key().as_ref()],
+        bump = encounter.bump
+    )]
+    pub encounter: Account<'info, Encounter>,
+}
+
+#[derive(Accounts)]
+pub struct FleeEncounter<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"encounter", owner.key().as_ref()],
+        bump = encounter.bump
+    )]
+    pub encounter: Account<'info, Encounter>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(item_id: u16)]
+pub struct CreateItem<'info> {
+    #[account(
+        init,
         payer = authority,
-        space = 8 + GameState::INIT_SPACE,
-        seeds = [b"game_state"],
+        space = 8 + Item::INIT_SPACE,
+        seeds = [b"item", item_id.to_le_bytes().as_ref()],
         bump
     )]
+    pub item: Account<'info, Item>,
+    #[account(has_one = authority)]
     pub game_state: Account<'info, GameState>,
     #[account(mut)]
     pub authority: Signer<'info>,
-    pub poke_coin_mint: Account<'info, Mint>,
-    /// CHECK: Treasury account
-    pub treasury: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct RegisterTrainer<'info> {
+pub struct UpdateItem<'info> {
+    #[account(
+        mut,
+        seeds = [b"item", item.id.to_le_bytes().as_ref()],
+        bump = item.bump
+    )]
+    pub item: Account<'info, Item>,
+    #[account(has_one = authority)]
+    pub game_state: Account<'info, GameState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BuyItem<'info> {
+    #[account(
+        mut,
+        seeds = [b"item", item.id.to_le_bytes().as_ref()],
+        bump = item.bump
+    )]
+    pub item: Account<'info, Item>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + Inventory::INIT_SPACE,
+        seeds = [b"inventory", buyer.key().as_ref()],
+        bump
+    )]
+    pub inventory: Account<'info, Inventory>,
+    pub game_state: Account<'info, GameState>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = poke_coin_mint,
+        token::authority = buyer,
+        token::token_program = token_program,
+    )]
+    pub buyer_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, address = game_state.treasury)]
+    pub treasury_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengeBattle<'info> {
     #[account(
         init,
-        payer = authority,
-        space = 8 + Trainer::INIT_SPACE,
-        seeds = [b"trainer", authority.key().as_ref()],
+        payer = challenger_authority,
+        space = 8 + Battle::INIT_SPACE,
+        seeds = [b"battle", game_state.total_battles.to_le_bytes().as_ref()],
         bump
     )]
-    pub trainer: Account<'info, Trainer>,
+    pub battle: Box<Account<'info, Battle>>,
+    #[account(mut)]
+    pub game_state: Box<Account<'info, GameState>>,
+    #[account(
+        seeds = [b"trainer", challenger_authority.key().as_ref()],
+        bump = challenger.bump
+    )]
+    pub challenger: Account<'info, Trainer>,
+    /// CHECK: Opponent wallet
+    pub opponent: AccountInfo<'info>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = poke_coin_mint,
+        token::authority = challenger_authority,
+        token::token_program = token_program,
+    )]
+    pub challenger_coin_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = challenger_authority,
+        associated_token::mint = poke_coin_mint,
+        associated_token::authority = battle,
+        associated_token::token_program = token_program,
+    )]
+    pub wager_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub challenger_authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptBattle<'info> {
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    pub game_state: Account<'info, GameState>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = poke_coin_mint,
+        token::authority = opponent,
+        token::token_program = token_program,
+    )]
+    pub opponent_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = poke_coin_mint,
+        associated_token::authority = battle,
+        associated_token::token_program = token_program,
+    )]
+    pub wager_vault: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
+    pub opponent: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBattle<'info> {
+    #[account(
+        mut,
+        close = challenger_authority,
+        constraint = battle.challenger == challenger_authority.key() @ ErrorCode::TrainerNotInBattle,
+        seeds = [b"battle", battle.id.to_le_bytes().as_ref()],
+        bump = battle.bump
+    )]
+    pub battle: Account<'info, Battle>,
     pub game_state: Account<'info, GameState>,
+    /// Mutable so transfer fees withheld in the vault can be harvested before it closes
+    #[account(mut, address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = poke_coin_mint,
+        token::authority = challenger_authority,
+        token::token_program = token_program,
+    )]
+    pub challenger_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = poke_coin_mint,
+        associated_token::authority = battle,
+        associated_token::token_program = token_program,
+    )]
+    pub wager_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub challenger_authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBattleTimeout<'info> {
+    #[account(
+        mut,
+        seeds = [b"battle", battle.id.to_le_bytes().as_ref()],
+        bump = battle.bump
+    )]
+    pub battle: Account<'info, Battle>,
+}
+
+#[derive(Accounts)]
+pub struct SelectPokemon<'info> {
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    #[account(
+        seeds = [b"trainer", trainer_authority.key().as_ref()],
+        bump = trainer.bump
+    )]
+    pub trainer: Account<'info, Trainer>,
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    /// Required when selecting a rented Pokemon
+    #[account(
+        seeds = [b"rental", pokemon.mint.as_ref()],
+        bump = rental.bump
+    )]
+    pub rental: Option<Account<'info, Rental>>,
+    pub trainer_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(move_id: u16)]
+pub struct UseMove<'info> {
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    #[account(mut)]
+    pub attacker_pokemon: Account<'info, Pokemon>,
+    #[account(mut)]
+    pub defender_pokemon: Account<'info, Pokemon>,
+    #[account(
+        seeds = [b"move", move_id.to_le_bytes().as_ref()],
+        bump = move_def.bump
+    )]
+    pub move_def: Account<'info, MoveDef>,
+    pub trainer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GiveItem<'info> {
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(
+        seeds = [b"item", item.id.to_le_bytes().as_ref()],
+        bump = item.bump
+    )]
+    pub item: Account<'info, Item>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"inventory", owner.key().as_ref()],
+        bump = inventory.bump
+    )]
+    pub inventory: Account<'info, Inventory>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TakeItem<'info> {
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"inventory", owner.key().as_ref()],
+        bump = inventory.bump
+    )]
+    pub inventory: Account<'info, Inventory>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UseItem<'info> {
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(
+        seeds = [b"item", item.id.to_le_bytes().as_ref()],
+        bump = item.bump
+    )]
+    pub item: Account<'info, Item>,
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [b"inventory", owner.key().as_ref()],
+        bump = inventory.bump
+    )]
+    pub inventory: Account<'info, Inventory>,
+    /// CHECK: The owner's PvE battle PDA, which may not exist; healing is blocked while it is active
+    #[account(
+        seeds = [b"pve_battle", owner.key().as_ref()],
+        bump
+    )]
+    pub pve_battle: AccountInfo<'info>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UseBattleItem<'info> {
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    #[account(mut)]
+    pub pokemon: Account<'info, Pokemon>,
+    #[account(
+        seeds = [b"item", item.id.to_le_bytes().as_ref()],
+        bump = item.bump
+    )]
+    pub item: Account<'info, Item>,
+    #[account(
+        mut,
+        seeds = [b"inventory", trainer.key().as_ref()],
+        bump = inventory.bump
+    )]
+    pub inventory: Account<'info, Inventory>,
+    pub trainer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EndBattle<'info> {
+    #[account(
+        mut,
+        close = challenger_authority,
+        seeds = [b"battle", battle.id.to_le_bytes().as_ref()],
+        bump = battle.bump
+    )]
+    pub battle: Box<Account<'info, Battle>>,
     #[account(mut)]
+    pub game_state: Box<Account<'info, GameState>>,
+    #[account(
+        mut,
+        seeds = [b"trainer", winner_trainer.public_key.as_ref()],
+        bump = winner_trainer.bump
+    )]
+    pub winner_trainer: Box<Account<'info, Trainer>>,
+    #[account(
+        mut,
+        seeds = [b"trainer", loser_trainer.public_key.as_ref()],
+        bump = loser_trainer.bump
+    )]
+    pub loser_trainer: Box<Account<'info, Trainer>>,
+    #[account(
+        mut,
+        token::mint = poke_coin_mint,
+        token::authority = winner_trainer.public_key,
+        token::token_program = token_program,
+    )]
+    pub winner_coin_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = poke_coin_mint,
+        associated_token::authority = battle,
+        associated_token::token_program = token_program,
+    )]
+    pub wager_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        address = game_state.treasury,
+        token::authority = game_state,
+        token::token_program = token_program,
+    )]
+    pub treasury_coin_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Mutable so transfer fees withheld in the vault can be harvested before it closes
+    #[account(mut, address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Challenger wallet, checked against the battle; receives the battle and vault rent
+    #[account(mut, address = battle.challenger)]
+    pub challenger_authority: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGameConfig<'info> {
+    #[account(mut, has_one = authority)]
+    pub game_state: Account<'info, GameState>,
     pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ListPokemon<'info> {
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + Listing::INIT_SPACE,
+        seeds = [b"listing", pokemon_mint.key().as_ref()],
+        bump
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(
+        mut,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump = pokemon.bump
+    )]
+    pub pokemon: Account<'info, Pokemon>,
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program,
+    )]
+    pub seller_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = listing,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelListing<'info> {
+    #[account(
+        mut,
+        close = seller,
+        has_one = seller,
+        seeds = [b"listing", pokemon_mint.key().as_ref()],
+        bump = listing.bump
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(
+        mut,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump = pokemon.bump
+    )]
+    pub pokemon: Account<'info, Pokemon>,
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = listing,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program,
+    )]
+    pub seller_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct BuyPokemon<'info> {
+    #[account(
+        mut,
+        close = seller,
+        has_one = seller,
+        seeds = [b"listing", pokemon_mint.key().as_ref()],
+        bump = listing.bump
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(
+        mut,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump = pokemon.bump
+    )]
+    pub pokemon: Account<'info, Pokemon>,
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    pub game_state: Account<'info, GameState>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = listing,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program,
+    )]
+    pub buyer_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"trainer", buyer.key().as_ref()],
+        bump = buyer_trainer.bump
+    )]
+    pub buyer_trainer: Account<'info, Trainer>,
+    #[account(
+        mut,
+        seeds = [b"trainer", seller.key().as_ref()],
+        bump = seller_trainer.bump
+    )]
+    pub seller_trainer: Account<'info, Trainer>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = game_state.poke_coin_mint,
+        token::authority = buyer,
+        token::token_program = coin_token_program,
+    )]
+    pub buyer_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = game_state.poke_coin_mint,
+        token::authority = seller,
+        token::token_program = coin_token_program,
+    )]
+    pub seller_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, address = game_state.treasury)]
+    pub treasury_coin_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Seller wallet, checked against the listing; receives the listing rent
+    #[account(mut)]
+    pub seller: AccountInfo<'info>,
     #[account(mut)]
-    pub poke_coin_mint: Account<'info, Mint>,
+    pub buyer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub coin_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateAuction<'info> {
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + Auction::INIT_SPACE,
+        seeds = [b"auction", pokemon_mint.key().as_ref()],
+        bump
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        mut,
+        seeds = [b"pokemon", pokemon_mint.key().as_ref()],
+        bump = pokemon.bump
+    )]
+    pub pokemon: Account<'info, Pokemon>,
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    pub game_state: Account<'info, GameState>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program,
+    )]
+    pub seller_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = auction,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pokemon_account: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init,
-        payer = authority,
+        payer = seller,
         associated_token::mint = poke_coin_mint,
-        associated_token::authority = authority,
+        associated_token::authority = auction,
+        associated_token::token_program = coin_token_program,
     )]
-    pub trainer_coin_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub bid_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub coin_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CatchPokemon<'info> {
-    #[account(mut)]
-    pub trainer: Account<'info, Trainer>,
-    #[account(mut)]
+pub struct PlaceBid<'info> {
+    #[account(
+        mut,
+        seeds = [b"auction", auction.pokemon_mint.as_ref()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
     pub game_state: Account<'info, GameState>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
     #[account(
-        init,
-        payer = payer,
-        space = 8 + Pokemon::INIT_SPACE,
+        mut,
+        associated_token::mint = poke_coin_mint,
+        associated_token::authority = auction,
+        associated_token::token_program = token_program,
+    )]
+    pub bid_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = poke_coin_mint, token::authority = bidder)]
+    pub bidder_coin_account: InterfaceAccount<'info, TokenAccount>,
+    /// Required once the auction has a bid, to refund the outbid bidder
+    #[account(mut, token::mint = poke_coin_mint)]
+    pub previous_bidder_coin_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Settlement moves the Pokemon into the winner's trainer, so bidders must have one
+    #[account(
+        seeds = [b"trainer", bidder.key().as_ref()],
+        bump = bidder_trainer.bump
+    )]
+    pub bidder_trainer: Account<'info, Trainer>,
+    pub bidder: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    #[account(
+        mut,
+        close = seller,
+        has_one = seller,
+        seeds = [b"auction", pokemon_mint.key().as_ref()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        mut,
         seeds = [b"pokemon", pokemon_mint.key().as_ref()],
-        bump
+        bump = pokemon.bump
     )]
     pub pokemon: Account<'info, Pokemon>,
+    pub pokemon_mint: InterfaceAccount<'info, Mint>,
+    pub game_state: Account<'info, GameState>,
     #[account(
-        init,
-        payer = payer,
-        mint::decimals = 0,
-        mint::authority = game_state,
+        mut,
+        associated_token::mint = pokemon_mint,
+        associated_token::authority = auction,
+        associated_token::token_program = token_program,
     )]
-    pub pokemon_mint: Account<'info, Mint>,
+    pub escrow_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    /// Mutable so transfer fees withheld in the vault can be harvested before it closes
+    #[account(mut, address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
     #[account(
-        init,
+        mut,
+        associated_token::mint = poke_coin_mint,
+        associated_token::authority = auction,
+        associated_token::token_program = coin_token_program,
+    )]
+    pub bid_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Highest bidder, or the seller when there were no bids
+    #[account(constraint = recipient.key() == auction.highest_bidder.unwrap_or(auction.seller) @ ErrorCode::InvalidAuctionRecipient)]
+    pub recipient: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
         payer = payer,
         associated_token::mint = pokemon_mint,
-        associated_token::authority = trainer_authority,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program,
+    )]
+    pub recipient_pokemon_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"trainer", recipient.key().as_ref()],
+        bump = recipient_trainer.bump
+    )]
+    pub recipient_trainer: Account<'info, Trainer>,
+    #[account(
+        mut,
+        seeds = [b"trainer", seller.key().as_ref()],
+        bump = seller_trainer.bump
+    )]
+    pub seller_trainer: Account<'info, Trainer>,
+    #[account(
+        mut,
+        token::mint = game_state.poke_coin_mint,
+        token::authority = seller,
+        token::token_program = coin_token_program,
     )]
-    pub trainer_pokemon_account: Account<'info, TokenAccount>,
-    /// CHECK: Metadata account
+    pub seller_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, address = game_state.treasury)]
+    pub treasury_coin_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Seller wallet, checked against the auction; receives the escrow rent
     #[account(mut)]
-    pub metadata_account: AccountInfo<'info>,
+    pub seller: AccountInfo<'info>,
     #[account(mut)]
     pub payer: Signer<'info>,
-    pub trainer_authority: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub coin_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    /// CHECK: Token Metadata Program
-    pub token_metadata_program: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
-// Additional account structs for other instructions...
 #[derive(Accounts)]
-pub struct ChallengeBattle<'info> {
+pub struct ProposeTrade<'info> {
     #[account(
         init,
-        payer = challenger,
-        space = 8 + Battle::INIT_SPACE,
-        seeds = [b"battle", game_state.total_battles.to_le_bytes().as_ref()],
+        payer = proposer,
+        space = 8 + TradeOffer::INIT_SPACE,
+        seeds = [b"trade", proposer.key().as_ref(), recipient.key().as_ref()],
         bump
     )]
-    pub battle: Account<'info, Battle>,
-    #[account(mut)]
+    pub trade: Account<'info, TradeOffer>,
+    #[account(
+        seeds = [b"trainer", recipient.key().as_ref()],
+        bump = recipient_trainer.bump
+    )]
+    pub recipient_trainer: Account<'info, Trainer>,
+    /// CHECK: Recipient wallet, must have a registered trainer
+    pub recipient: AccountInfo<'info>,
     pub game_state: Account<'info, GameState>,
+    #[account(address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = poke_coin_mint,
+        token::authority = proposer,
+        token::token_program = coin_token_program,
+    )]
+    pub proposer_coin_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = proposer,
+        associated_token::mint = poke_coin_mint,
+        associated_token::authority = trade,
+        associated_token::token_program = coin_token_program,
+    )]
+    pub coin_vault: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
-    pub challenger: Account<'info, Trainer>,
-    /// CHECK: Opponent trainer account
-    pub opponent: AccountInfo<'info>,
+    pub proposer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub coin_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct AcceptBattle<'info> {
-    #[account(mut)]
-    pub battle: Account<'info, Battle>,
-    #[account(mut)]
-    pub opponent: Signer<'info>,
-}
-
-#[derive(Accounts)]
-pub struct SelectPokemon<'info> {
-    #[account(mut)]
-    pub battle: Account<'info, Battle>,
-    pub trainer: Account<'info, Trainer>,
-    pub pokemon: Account<'info, Pokemon>,
-    pub trainer_authority: Signer<'info>,
-}
-
-#[derive(Accounts)]
-pub struct UseMove<'info> {
-    #[account(mut)]
-    pub battle: Account<'info, Battle>,
+pub struct AcceptTrade<'info> {
+    #[account(
+        mut,
+        close = proposer,
+        has_one = proposer,
+        has_one = recipient,
+        seeds = [b"trade", proposer.key().as_ref(), recipient.key().as_ref()],
+        bump = trade.bump
+    )]
+    pub trade: Account<'info, TradeOffer>,
+    #[account(
+        mut,
+        seeds = [b"trainer", proposer.key().as_ref()],
+        bump = proposer_trainer.bump
+    )]
+    pub proposer_trainer: Account<'info, Trainer>,
+    #[account(
+        mut,
+        seeds = [b"trainer", recipient.key().as_ref()],
+        bump = recipient_trainer.bump
+    )]
+    pub recipient_trainer: Account<'info, Trainer>,
+    pub game_state: Account<'info, GameState>,
+    /// Mutable so transfer fees withheld in the vault can be harvested before it closes
+    #[account(mut, address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = poke_coin_mint,
+        associated_token::authority = trade,
+        associated_token::token_program = coin_token_program,
+    )]
+    pub coin_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = poke_coin_mint, token::authority = recipient)]
+    pub recipient_coin_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Proposer wallet, checked against the trade; receives the escrow rent
     #[account(mut)]
-    pub attacker_pokemon: Account<'info, Pokemon>,
+    pub proposer: AccountInfo<'info>,
     #[account(mut)]
-    pub defender_pokemon: Account<'info, Pokemon>,
-    pub trainer: Signer<'info>,
+    pub recipient: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub coin_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct EndBattle<'info> {
-    #[account(mut)]
-    pub battle: Account<'info, Battle>,
-    #[account(mut)]
-    pub winner_trainer: Account<'info, Trainer>,
-    #[account(mut)]
-    pub loser_trainer: Account<'info, Trainer>,
-    #[account(mut)]
-    pub winner_coin_account: Account<'info, TokenAccount>,
+pub struct CloseTrade<'info> {
+    #[account(
+        mut,
+        close = proposer,
+        has_one = proposer,
+        seeds = [b"trade", trade.proposer.as_ref(), trade.recipient.as_ref()],
+        bump = trade.bump
+    )]
+    pub trade: Account<'info, TradeOffer>,
+    pub game_state: Account<'info, GameState>,
+    /// Mutable so transfer fees withheld in the vault can be harvested before it closes
+    #[account(mut, address = game_state.poke_coin_mint)]
+    pub poke_coin_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = poke_coin_mint,
+        associated_token::authority = trade,
+        associated_token::token_program = coin_token_program,
+    )]
+    pub coin_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = poke_coin_mint, token::authority = proposer)]
+    pub proposer_coin_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Proposer wallet, checked against the trade; receives the escrow rent
     #[account(mut)]
-    pub loser_coin_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub proposer: AccountInfo<'info>,
+    /// Proposer (cancel) or recipient (reject)
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub coin_token_program: Interface<'info, TokenInterface>,
 }
 
 // Data structures
@@ -527,76 +5416,306 @@ pub struct GameState {
     pub authority: Pubkey,
     pub poke_coin_mint: Pubkey,
     pub treasury: Pubkey,
+    pub collection_mint: Pubkey,
+    pub pokemon_tree: Pubkey,
     pub total_battles: u64,
     pub total_trainers: u64,
     pub total_pokemon: u64,
     pub season: u32,
+    pub season_emission_cap: u64,
+    pub season_emitted: u64,
+    pub battle_item_limit: u8,
+    pub marketplace_fee_bps: u16,
+    pub royalty_bps: u16,
+    #[max_len(100)]
+    pub base_uri: String,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Trainer {
+    pub public_key: Pubkey,
+    #[max_len(32)]
+    pub username: String,
+    pub level: u8,
+    pub experience: u32,
+    pub battles_won: u32,
+    pub battles_lost: u32,
+    pub pokemon_caught: u32,
+    pub poke_coins: u64,
+    #[max_len(8)]
+    pub badges: Vec<u8>,
+    #[max_len(6)]
+    pub pokemon_team: Vec<Pubkey>,
+    #[max_len(100)]
+    pub pokemon_box: Vec<Pubkey>,
+    pub last_encounter_at: i64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Pokemon {
+    pub trainer: Pubkey,
+    pub species_id: u16,
+    #[max_len(20)]
+    pub name: String,
+    #[max_len(12)]
+    pub nickname: Option<String>,
+    pub level: u8,
+    pub experience: u32,
+    pub base_stats: [u16; 6],
+    pub hp: u16,
+    pub attack: u16,
+    pub defense: u16,
+    pub sp_attack: u16,
+    pub sp_defense: u16,
+    pub speed: u16,
+    #[max_len(2)]
+    pub types: Vec<PokemonType>,
+    #[max_len(4)]
+    pub moves: Vec<u16>,
+    pub nature: Nature,
+    pub is_shiny: bool,
+    pub status: PokemonStatus,
+    pub battle: Option<Pubkey>, // the PvP battle it was last sent out in
+    pub condition: Option<StatusCondition>,
+    pub held_item: Option<HeldItem>,
+    pub ability: Ability,
+    pub traded: bool, // received in a trade, so a trade evolution can trigger
+    pub caught_at: i64,
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Battle {
+    pub id: u64,
+    pub challenger: Pubkey,
+    pub opponent: Pubkey,
+    pub wager: u64,
+    pub state: BattleState,
+    pub current_turn: u16,
+    pub challenger_active_pokemon: Option<Pubkey>,
+    pub opponent_active_pokemon: Option<Pubkey>,
+    pub item_limit: u8,
+    pub challenger_items_used: u8,
+    pub opponent_items_used: u8,
+    pub challenger_stat_stages: [i8; 6],
+    pub opponent_stat_stages: [i8; 6],
+    pub weather: Option<Weather>,
+    pub weather_turns: u8,
+    pub challenger_hazards: Hazards,
+    pub opponent_hazards: Hazards,
+    pub winner: Option<u8>,
+    pub created_at: i64,
+    pub started_at: Option<i64>,
+    pub last_action_at: i64,
+    pub ended_at: Option<i64>,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Listing {
+    pub seller: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub price: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Auction {
+    pub seller: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub reserve_price: u64,
+    pub min_bid_increment: u64,
+    pub highest_bidder: Option<Pubkey>,
+    pub highest_bid: u64,
+    pub end_time: i64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Stake {
+    pub owner: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub last_claim_slot: u64,
+    pub staked_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Species {
+    pub species_id: u16,
+    pub data: PokemonData,
+    pub catch_rate: u8, // 1..=255, higher is easier
+    pub trade_evolution: Option<u16>, // species it evolves into when traded
+    #[max_len(3)]
+    pub abilities: Vec<Ability>,
+    pub bump: u8,
+}
+
+/// A zone's spawn table for wild encounters
+#[account]
+#[derive(InitSpace)]
+pub struct SpawnZone {
+    pub zone_id: u16,
+    #[max_len(16)]
+    pub spawns: Vec<Spawn>,
+    pub bump: u8,
+}
+
+/// A program-controlled trainer whose team can be battled in PvE
+#[account]
+#[derive(InitSpace)]
+pub struct NpcTrainer {
+    pub npc_id: u16,
+    #[max_len(32)]
+    pub name: String,
+    #[max_len(6)]
+    pub team: Vec<NpcPokemon>,
+    pub bump: u8,
+}
+
+/// A gym whose leader is battled in PvE for a badge
+#[account]
+#[derive(InitSpace)]
+pub struct Gym {
+    pub gym_id: u16,
+    #[max_len(6)]
+    pub leader_team: Vec<NpcPokemon>,
+    pub level_cap: u8,
+    pub type_specialty: PokemonType,
+    pub badge_id: u8,
+    pub reward: u64, // whole PokeCoins
+    pub bump: u8,
+}
+
+/// Registry entry tying a badge ID to the one gym that awards it
+#[account]
+#[derive(InitSpace)]
+pub struct Badge {
+    pub badge_id: u8,
+    pub gym_id: u16,
+    pub bump: u8,
+}
+
+/// A trainer's battle against a program-controlled team. Opponents are fought in order and
+/// each is sent out at full HP once the previous one faints.
+#[account]
+#[derive(InitSpace)]
+pub struct PveBattle {
+    pub trainer: Pubkey,
+    pub opponent: PveOpponent,
+    #[max_len(6)]
+    pub opponent_team: Vec<NpcPokemon>,
+    pub opponent_index: u8,
+    pub opponent_hp: Option<u16>, // None until the current opponent has been sent out
+    pub level_cap: Option<u8>,
+    #[max_len(6)]
+    pub team: Vec<Pubkey>, // the trainer's team when the battle started
+    #[max_len(6)]
+    pub fainted: Vec<Pubkey>,
+    pub active_pokemon: Option<Pubkey>,
+    pub trainer_stat_stages: [i8; 6],
+    pub opponent_stat_stages: [i8; 6],
+    pub turn: u16,
+    pub state: BattleState,
+    pub won: bool,
+    pub started_at: i64,
+    pub bump: u8,
+}
+
+/// A trainer's wild encounter: committed by `encounter_wild`, rolled by `reveal_encounter`,
+/// then open until the Pokemon is claimed or the trainer flees
+#[account]
+#[derive(InitSpace)]
+pub struct Encounter {
+    pub trainer: Pubkey,
+    pub zone_id: u16,
+    pub commitment: [u8; 32],
+    pub commit_slot: u64,
+    pub seed: [u8; 32],
+    pub species_id: Option<u16>,
+    pub level: u8,
+    pub hp: u16,
+    pub catch_attempts: u8,
+    pub pending_throw: Option<PendingThrow>,
+    pub caught: bool,
+    pub bump: u8,
+}
+
+/// A move's type and power, plus the field effect it leaves behind, if any. Moves with no
+/// power deal no damage.
+#[account]
+#[derive(InitSpace)]
+pub struct MoveDef {
+    pub move_id: u16,
+    pub move_type: PokemonType,
+    pub power: u16,
+    pub effect: Option<MoveEffect>,
+    pub bump: u8,
+}
+
+/// A shop item. `effect` depends on the kind: HP restored by potions and revives, catch rate
+/// bonus of Poke Balls, move taught by a TM, species a stone evolves into, stat raised by an
+/// X item (indexed like the base stats), passive effect of a held item, and so on.
+#[account]
+#[derive(InitSpace)]
+pub struct Item {
+    pub id: u16,
+    #[max_len(32)]
+    pub name: String,
+    pub kind: ItemKind,
+    pub effect: u16,
+    pub price: u64,
+    pub stock: Option<u32>,
     pub bump: u8,
 }
 
 #[account]
 #[derive(InitSpace)]
-pub struct Trainer {
-    pub public_key: Pubkey,
+pub struct Inventory {
+    pub owner: Pubkey,
     #[max_len(32)]
-    pub username: String,
-    pub level: u8,
-    pub experience: u32,
-    pub battles_won: u32,
-    pub battles_lost: u32,
-    pub pokemon_caught: u32,
-    pub poke_coins: u64,
-    #[max_len(8)]
-    pub badges: Vec<u8>,
-    #[max_len(6)]
-    pub pokemon_team: Vec<Pubkey>,
-    #[max_len(100)]
-    pub pokemon_box: Vec<Pubkey>,
-    pub created_at: i64,
+    pub items: Vec<ItemStack>,
     pub bump: u8,
 }
 
+/// A Pokemon offered for rent. `borrower` and `expires_at` are set once someone rents it.
 #[account]
 #[derive(InitSpace)]
-pub struct Pokemon {
-    pub trainer: Pubkey,
-    pub species_id: u16,
-    #[max_len(20)]
-    pub name: String,
-    pub level: u8,
-    pub experience: u32,
-    pub hp: u16,
-    pub attack: u16,
-    pub defense: u16,
-    pub sp_attack: u16,
-    pub sp_defense: u16,
-    pub speed: u16,
-    #[max_len(2)]
-    pub types: Vec<PokemonType>,
-    #[max_len(4)]
-    pub moves: Vec<u16>,
-    pub nature: Nature,
-    pub is_shiny: bool,
-    pub caught_at: i64,
-    pub mint: Pubkey,
+pub struct Rental {
+    pub owner: Pubkey,
+    pub borrower: Option<Pubkey>,
+    pub pokemon_mint: Pubkey,
+    pub fee: u64,
+    pub duration: i64,
+    pub expires_at: i64,
+    pub created_at: i64,
     pub bump: u8,
 }
 
 #[account]
 #[derive(InitSpace)]
-pub struct Battle {
-    pub id: u64,
-    pub challenger: Pubkey,
-    pub opponent: Pubkey,
-    pub wager: u64,
-    pub state: BattleState,
-    pub current_turn: u16,
-    pub challenger_active_pokemon: Option<Pubkey>,
-    pub opponent_active_pokemon: Option<Pubkey>,
-    pub winner: Option<u8>,
+pub struct TradeOffer {
+    pub proposer: Pubkey,
+    pub recipient: Pubkey,
+    #[max_len(6)]
+    pub offered_mints: Vec<Pubkey>,
+    #[max_len(6)]
+    pub requested_mints: Vec<Pubkey>,
+    pub coin_amount: u64,
     pub created_at: i64,
-    pub started_at: Option<i64>,
-    pub ended_at: Option<i64>,
     pub bump: u8,
 }
 
@@ -617,6 +5736,121 @@ pub struct PokemonData {
     pub moves: Vec<u16>,
 }
 
+/// An NPC team member; with no moves it uses its species' default moves
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct NpcPokemon {
+    pub species_id: u16,
+    pub level: u8,
+    #[max_len(4)]
+    pub moves: Vec<u16>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum PveOpponent {
+    Npc(u16),
+    Gym(u16),
+}
+
+/// A committed Poke Ball throw waiting for its reveal
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct PendingThrow {
+    pub item_id: u16,
+    pub chance: u32,
+    pub commitment: [u8; 32],
+    pub commit_slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct Spawn {
+    pub species_id: u16,
+    pub weight: u16,
+    pub min_level: u8,
+    pub max_level: u8,
+}
+
+/// Game config a compressed Pokemon was minted under. It is part of the leaf hash, so the
+/// exact values from `CompressedPokemonMinted` must be passed back on decompression.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CompressedMintConfig {
+    pub base_uri: String,
+    pub royalty_bps: u16,
+    pub royalty_recipient: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct ItemStack {
+    pub item_id: u16,
+    pub quantity: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum ItemKind {
+    Potion,
+    Revive,
+    PokeBall,
+    HeldItem,
+    TechnicalMachine,
+    EvolutionStone,
+    StatusHeal,
+    BattleBoost,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct HeldItem {
+    pub item_id: u16,
+    pub effect: HeldItemEffect,
+}
+
+/// Passive battle effect of a held item
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum HeldItemEffect {
+    Leftovers,
+    ChoiceBand,
+    Berry,
+    FocusSash,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum Ability {
+    Intimidate,
+    Levitate,
+    Blaze,
+    Torrent,
+    Overgrow,
+    Static,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum MoveEffect {
+    Weather(Weather),
+    StealthRock,
+    Spikes,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum Weather {
+    Sun,
+    Rain,
+    Sandstorm,
+    Hail,
+}
+
+/// Entry hazards on one side of the field, hurting each Pokemon sent in on that side
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct Hazards {
+    pub stealth_rock: bool,
+    pub spikes: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum StatusCondition {
+    Poison,
+    Burn,
+    Paralysis,
+    Sleep,
+    Freeze,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum PokemonType {
     Normal, Fire, Water, Electric, Grass, Ice, Fighting, Poison,
@@ -632,6 +5866,17 @@ pub enum Nature {
     Calm, Gentle, Sassy, Careful, Quirky,
 }
 
+/// What a Pokemon is currently committed to; only `Active` Pokemon can be listed or battle,
+/// except a `Lent` one battling for its borrower
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum PokemonStatus {
+    Active,
+    Listed,
+    InTrade,
+    Staked,
+    Lent,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum BattleState {
     Challenged,
@@ -656,6 +5901,178 @@ pub struct PokemonCaught {
     pub name: String,
 }
 
+#[event]
+pub struct CompressedPokemonMinted {
+    pub leaf_owner: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub species_id: u16,
+    pub config: CompressedMintConfig,
+}
+
+#[event]
+pub struct PokemonDecompressed {
+    pub trainer: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct PokemonReleased {
+    pub trainer: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub species_id: u16,
+    pub reward: u64,
+}
+
+#[event]
+pub struct PokemonStaked {
+    pub owner: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub value_per_day: u64,
+}
+
+#[event]
+pub struct StakingRewardsClaimed {
+    pub owner: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub reward: u64,
+}
+
+#[event]
+pub struct PokemonUnstaked {
+    pub owner: Pubkey,
+    pub pokemon_mint: Pubkey,
+}
+
+#[event]
+pub struct RentalOffered {
+    pub owner: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub fee: u64,
+    pub duration: i64,
+}
+
+#[event]
+pub struct PokemonRented {
+    pub owner: Pubkey,
+    pub borrower: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub fee: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct RentalEnded {
+    pub owner: Pubkey,
+    pub borrower: Option<Pubkey>,
+    pub pokemon_mint: Pubkey,
+}
+
+#[event]
+pub struct ItemPurchased {
+    pub buyer: Pubkey,
+    pub item_id: u16,
+    pub quantity: u32,
+    pub cost: u64,
+}
+
+#[event]
+pub struct ItemUsed {
+    pub trainer: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub item_id: u16,
+    pub battle_id: Option<u64>,
+}
+
+#[event]
+pub struct HeldItemChanged {
+    pub pokemon_mint: Pubkey,
+    pub item_id: Option<u16>,
+}
+
+#[event]
+pub struct HeldItemConsumed {
+    pub pokemon_mint: Pubkey,
+    pub item_id: u16,
+}
+
+#[event]
+pub struct AbilityActivated {
+    pub pokemon_mint: Pubkey,
+    pub ability: Ability,
+}
+
+#[event]
+pub struct WildPokemonAppeared {
+    pub trainer: Pubkey,
+    pub zone_id: u16,
+    pub species_id: u16,
+    pub level: u8,
+    pub commit_slot: u64,
+    pub slot_hash: [u8; 32],
+    pub seed: [u8; 32],
+}
+
+#[event]
+pub struct WildPokemonAttacked {
+    pub trainer: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub move_id: u16,
+    pub damage: u16,
+    pub wild_hp: u16,
+    pub wild_move_id: Option<u16>,
+    pub wild_damage: u16,
+}
+
+#[event]
+pub struct CatchAttempted {
+    pub trainer: Pubkey,
+    pub species_id: u16,
+    pub item_id: u16,
+    pub chance: u32,
+    pub roll: u32,
+    pub commit_slot: u64,
+    pub slot_hash: [u8; 32],
+    pub caught: bool,
+}
+
+#[event]
+pub struct PveMoveUsed {
+    pub trainer: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub move_id: u16,
+    pub damage: u16,
+    pub opponent_species_id: u16,
+    pub opponent_move_id: Option<u16>,
+    pub opponent_damage: u16,
+}
+
+#[event]
+pub struct PveSwitched {
+    pub trainer: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub opponent_species_id: u16,
+    pub opponent_move_id: Option<u16>,
+    pub opponent_damage: u16,
+}
+
+#[event]
+pub struct PveBattleEnded {
+    pub trainer: Pubkey,
+    pub opponent: PveOpponent,
+    pub won: bool,
+    pub turns: u16,
+}
+
+#[event]
+pub struct BadgeEarned {
+    pub trainer: Pubkey,
+    pub gym_id: u16,
+    pub badge_id: u8,
+    pub reward: u64,
+}
+
 #[event]
 pub struct BattleChallenge {
     pub battle_id: u64,
@@ -698,6 +6115,108 @@ pub struct BattleRewardsDistributed {
     pub winner: Pubkey,
     pub loser: Pubkey,
     pub wager: u64,
+    pub reward: u64,
+    pub season: u32,
+}
+
+#[event]
+pub struct SeasonStarted {
+    pub season: u32,
+    pub emission_cap: u64,
+}
+
+#[event]
+pub struct PokemonListed {
+    pub seller: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub price: u64,
+}
+
+#[event]
+pub struct ListingCancelled {
+    pub seller: Pubkey,
+    pub pokemon_mint: Pubkey,
+}
+
+#[event]
+pub struct PokemonSold {
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub price: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct AuctionCreated {
+    pub seller: Pubkey,
+    pub pokemon_mint: Pubkey,
+    pub reserve_price: u64,
+    pub min_bid_increment: u64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct BidPlaced {
+    pub pokemon_mint: Pubkey,
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct AuctionSettled {
+    pub seller: Pubkey,
+    pub winner: Option<Pubkey>,
+    pub pokemon_mint: Pubkey,
+    pub price: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct PokemonNotCollected {
+    pub trainer: Pubkey,
+    pub pokemon_mint: Pubkey,
+}
+
+#[event]
+pub struct TradeProposed {
+    pub proposer: Pubkey,
+    pub recipient: Pubkey,
+    pub offered_mints: Vec<Pubkey>,
+    pub requested_mints: Vec<Pubkey>,
+    pub coin_amount: u64,
+}
+
+/// Emitted per Pokemon that changes hands in a trade, so trade evolutions can key off it
+#[event]
+pub struct PokemonTraded {
+    pub pokemon_mint: Pubkey,
+    pub species_id: u16,
+    pub from: Pubkey,
+    pub to: Pubkey,
+}
+
+#[event]
+pub struct TradeCompleted {
+    pub proposer: Pubkey,
+    pub recipient: Pubkey,
+}
+
+#[event]
+pub struct TradeClosed {
+    pub proposer: Pubkey,
+    pub recipient: Pubkey,
+    pub closed_by: Pubkey,
+}
+
+#[event]
+pub struct PokemonUpdated {
+    pub pokemon_mint: Pubkey,
+    pub species_id: u16,
+    pub level: u8,
+    pub nickname: Option<String>,
+    pub is_shiny: bool,
 }
 
 // Error codes
@@ -721,4 +6240,132 @@ pub enum ErrorCode {
     InvalidActivePokemon,
     #[msg("Battle not finished")]
     BattleNotFinished,
+    #[msg("Pokemon is not available")]
+    PokemonNotAvailable,
+    #[msg("Pokemon box is full")]
+    PokemonBoxFull,
+    #[msg("Price must be greater than zero")]
+    InvalidPrice,
+    #[msg("Fee exceeds the maximum")]
+    InvalidFee,
+    #[msg("Cannot buy your own listing")]
+    CannotBuyOwnListing,
+    #[msg("Invalid auction duration")]
+    InvalidAuctionDuration,
+    #[msg("Auction has ended")]
+    AuctionEnded,
+    #[msg("Auction has not ended yet")]
+    AuctionNotEnded,
+    #[msg("Bid is too low")]
+    BidTooLow,
+    #[msg("Previous bidder coin account required for refund")]
+    MissingRefundAccount,
+    #[msg("Auction recipient must be the highest bidder, or the seller without bids")]
+    InvalidAuctionRecipient,
+    #[msg("Invalid trade offer")]
+    InvalidTrade,
+    #[msg("Remaining accounts do not match the traded Pokemon")]
+    InvalidTradeAccounts,
+    #[msg("Only the other trade party can do this")]
+    UnauthorizedTradeParty,
+    #[msg("Base URI is too long")]
+    BaseUriTooLong,
+    #[msg("Nickname must be between 1 and 12 bytes")]
+    InvalidNickname,
+    #[msg("Not enough experience to level up")]
+    NotEnoughExperience,
+    #[msg("Compressed Pokemon data does not match the leaf")]
+    InvalidCompressedPokemon,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Cannot challenge yourself")]
+    CannotBattleSelf,
+    #[msg("Rental duration must be between 1 second and 30 days")]
+    InvalidRentalDuration,
+    #[msg("Pokemon is already rented")]
+    AlreadyRented,
+    #[msg("Cannot rent your own Pokemon")]
+    CannotRentOwnPokemon,
+    #[msg("Rental has expired")]
+    RentalExpired,
+    #[msg("Rental has not expired yet")]
+    RentalNotExpired,
+    #[msg("Item name must be between 1 and 32 bytes")]
+    InvalidItemName,
+    #[msg("Quantity must be greater than zero")]
+    InvalidQuantity,
+    #[msg("Not enough stock")]
+    ItemOutOfStock,
+    #[msg("Inventory is full")]
+    InventoryFull,
+    #[msg("Not enough of this item in the inventory")]
+    NotEnoughItems,
+    #[msg("This item cannot be used here")]
+    ItemNotUsable,
+    #[msg("The item would have no effect")]
+    ItemHasNoEffect,
+    #[msg("Item limit for this battle reached")]
+    BattleItemLimitReached,
+    #[msg("Pokemon is already holding an item")]
+    AlreadyHoldingItem,
+    #[msg("Pokemon is not holding an item")]
+    NotHoldingItem,
+    #[msg("A species has between 1 and 3 abilities")]
+    InvalidAbilities,
+    #[msg("Species account does not match the Pokemon")]
+    InvalidSpecies,
+    #[msg("Pokemon does not know this move")]
+    UnknownMove,
+    #[msg("Pokemon can only be minted directly by the game authority")]
+    DirectCatchNotAllowed,
+    #[msg("Catch rate must be greater than zero")]
+    InvalidCatchRate,
+    #[msg("Invalid spawn table")]
+    InvalidSpawnTable,
+    #[msg("Encounter has already been revealed")]
+    EncounterAlreadyRevealed,
+    #[msg("Secret does not match the encounter commitment")]
+    InvalidEncounterSecret,
+    #[msg("Encounter cannot be revealed yet")]
+    EncounterNotReady,
+    #[msg("Encounter commit slot is too old, flee and try again")]
+    EncounterExpired,
+    #[msg("No wild Pokemon to battle or catch")]
+    EncounterNotActive,
+    #[msg("Wild Pokemon has fainted")]
+    WildPokemonFainted,
+    #[msg("Wild Pokemon has not been caught")]
+    PokemonNotCaught,
+    #[msg("A thrown Poke Ball is waiting to be revealed")]
+    ThrowPending,
+    #[msg("No Poke Ball throw to reveal")]
+    NoPendingThrow,
+    #[msg("Invalid NPC trainer or team")]
+    InvalidNpcTeam,
+    #[msg("Move accounts don't match the opponent's moves")]
+    InvalidMoveAccounts,
+    #[msg("Invalid gym")]
+    InvalidGym,
+    #[msg("Badge already earned")]
+    BadgeAlreadyEarned,
+    #[msg("Pokemon is above the battle's level cap")]
+    AboveLevelCap,
+    #[msg("Battle was not won")]
+    BattleNotWon,
+    #[msg("Pokemon cannot evolve by trade")]
+    NoTradeEvolution,
+    #[msg("Pokemon is already active")]
+    PokemonAlreadyActive,
+    #[msg("Trainer already holds the maximum number of badges")]
+    TooManyBadges,
+    #[msg("Trainer has no Pokemon on their team")]
+    EmptyTeam,
+    #[msg("Items can't be used outside battle during a PvE battle")]
+    PveBattleInProgress,
+    #[msg("Wait for the encounter cooldown before walking into the grass again")]
+    EncounterCooldown,
+    #[msg("The trainer to act still has time for their turn")]
+    TurnNotTimedOut,
+    #[msg("Pokemon is in a battle that isn't over")]
+    PokemonInBattle,
 }
\ No newline at end of file
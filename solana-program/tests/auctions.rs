@@ -0,0 +1,110 @@
+mod common;
+
+use anchor_spl::token::spl_token;
+use common::{assert_error, pda, Game, COIN, PIKACHU};
+use pokemon_game::{Auction, ErrorCode, PokemonStatus, AUCTION_EXTENSION};
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn outbid_bidder_is_refunded() {
+    let mut game = Game::start(spl_token::ID).await;
+    let seller = game.register_trainer("misty").await;
+    let first = game.register_trainer("brock").await;
+    let second = game.register_trainer("ash").await;
+    let pokemon_mint = game.catch_pokemon(&seller, PIKACHU, "Pikachu").await;
+    game.create_auction(&seller, pokemon_mint, 100 * COIN, 60 * 60).await;
+    let bid_vault = game.coin_account(&pda(&[b"auction", pokemon_mint.as_ref()]));
+
+    assert_error(game.place_bid(&first, pokemon_mint, 99 * COIN, None).await, ErrorCode::BidTooLow);
+    game.place_bid(&first, pokemon_mint, 100 * COIN, None).await.unwrap();
+    assert_eq!(game.token_balance(game.coin_account(&first.pubkey())).await, 900 * COIN);
+
+    // A new bid must beat the highest by the minimum increment
+    assert_error(
+        game.place_bid(&second, pokemon_mint, 105 * COIN, Some(first.pubkey())).await,
+        ErrorCode::BidTooLow,
+    );
+    assert_error(game.place_bid(&second, pokemon_mint, 110 * COIN, None).await, ErrorCode::MissingRefundAccount);
+    game.place_bid(&second, pokemon_mint, 110 * COIN, Some(first.pubkey())).await.unwrap();
+
+    assert_eq!(game.token_balance(game.coin_account(&first.pubkey())).await, 1000 * COIN);
+    assert_eq!(game.token_balance(game.coin_account(&second.pubkey())).await, 890 * COIN);
+    assert_eq!(game.token_balance(bid_vault).await, 110 * COIN);
+}
+
+#[tokio::test]
+async fn late_bid_extends_auction() {
+    let mut game = Game::start(spl_token::ID).await;
+    let seller = game.register_trainer("misty").await;
+    let bidder = game.register_trainer("brock").await;
+    let pokemon_mint = game.catch_pokemon(&seller, PIKACHU, "Pikachu").await;
+    game.create_auction(&seller, pokemon_mint, 100 * COIN, 60 * 60).await;
+    let auction = pda(&[b"auction", pokemon_mint.as_ref()]);
+    let end_time = game.fetch::<Auction>(auction).await.end_time;
+
+    // An early bid leaves the end time alone
+    game.place_bid(&bidder, pokemon_mint, 100 * COIN, None).await.unwrap();
+    assert_eq!(game.fetch::<Auction>(auction).await.end_time, end_time);
+
+    // A bid in the last minutes pushes it back
+    game.warp_seconds(60 * 60 - 60).await;
+    game.place_bid(&bidder, pokemon_mint, 110 * COIN, Some(bidder.pubkey())).await.unwrap();
+    let now = game.now().await;
+    assert_eq!(game.fetch::<Auction>(auction).await.end_time, now + AUCTION_EXTENSION);
+
+    // Settling at the original end time is too early
+    game.warp_seconds(60).await;
+    assert_error(
+        game.settle_auction(pokemon_mint, seller.pubkey(), bidder.pubkey()).await,
+        ErrorCode::AuctionNotEnded,
+    );
+}
+
+#[tokio::test]
+async fn settle_auction_pays_seller_and_transfers_pokemon() {
+    let mut game = Game::start(spl_token::ID).await;
+    let seller = game.register_trainer("misty").await;
+    let bidder = game.register_trainer("brock").await;
+    let pokemon_mint = game.catch_pokemon(&seller, PIKACHU, "Pikachu").await;
+    game.create_auction(&seller, pokemon_mint, 100 * COIN, 60 * 60).await;
+    game.place_bid(&bidder, pokemon_mint, 200 * COIN, None).await.unwrap();
+
+    game.warp_seconds(60 * 60).await;
+    assert_error(
+        game.settle_auction(pokemon_mint, seller.pubkey(), seller.pubkey()).await,
+        ErrorCode::InvalidAuctionRecipient,
+    );
+    game.settle_auction(pokemon_mint, seller.pubkey(), bidder.pubkey()).await.unwrap();
+
+    // The seller gets the winning bid minus the 2.5% marketplace fee
+    let fee = 200 * COIN * 250 / 10_000;
+    assert_eq!(game.token_balance(game.coin_account(&seller.pubkey())).await, 1200 * COIN - fee);
+    assert_eq!(game.token_balance(game.coin_account(&bidder.pubkey())).await, 800 * COIN);
+    assert_eq!(game.token_balance(game.treasury).await, fee);
+
+    assert_eq!(game.token_balance(game.pokemon_account(&bidder.pubkey(), &pokemon_mint)).await, 1);
+    let pokemon = game.pokemon(&pokemon_mint).await;
+    assert_eq!(pokemon.trainer, bidder.pubkey());
+    assert!(pokemon.status == PokemonStatus::Active);
+    assert!(game.trainer(&bidder.pubkey()).await.pokemon_team.contains(&pokemon_mint));
+    assert!(!game.trainer(&seller.pubkey()).await.pokemon_team.contains(&pokemon_mint));
+
+    let auction = pda(&[b"auction", pokemon_mint.as_ref()]);
+    assert!(!game.exists(auction).await);
+    assert!(!game.exists(game.coin_account(&auction)).await);
+}
+
+#[tokio::test]
+async fn auction_without_bids_returns_pokemon() {
+    let mut game = Game::start(spl_token::ID).await;
+    let seller = game.register_trainer("misty").await;
+    let pokemon_mint = game.catch_pokemon(&seller, PIKACHU, "Pikachu").await;
+    game.create_auction(&seller, pokemon_mint, 100 * COIN, 60 * 60).await;
+
+    game.warp_seconds(60 * 60).await;
+    game.settle_auction(pokemon_mint, seller.pubkey(), seller.pubkey()).await.unwrap();
+
+    assert_eq!(game.token_balance(game.pokemon_account(&seller.pubkey(), &pokemon_mint)).await, 1);
+    assert_eq!(game.token_balance(game.coin_account(&seller.pubkey())).await, 1000 * COIN);
+    assert!(game.pokemon(&pokemon_mint).await.status == PokemonStatus::Active);
+}
@@ -0,0 +1,213 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use common::{assert_error, pda, Game, COIN, EEVEE, PIKACHU};
+use pokemon_game::{Battle, BattleState, ErrorCode, GameState, PokemonType, BATTLE_TURN_TIMEOUT};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const TACKLE: u16 = 1;
+
+/// Challenge `opponent` for `wager` and return the battle account
+async fn challenge(game: &mut Game, challenger: &Keypair, opponent: Pubkey, wager: u64) -> Pubkey {
+    let game_state = pda(&[b"game_state"]);
+    let battle_id = game.fetch::<GameState>(game_state).await.total_battles;
+    let battle = pda(&[b"battle", battle_id.to_le_bytes().as_ref()]);
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::ChallengeBattle {
+            battle,
+            game_state,
+            challenger: pda(&[b"trainer", challenger.pubkey().as_ref()]),
+            opponent,
+            poke_coin_mint: game.poke_coin_mint,
+            challenger_coin_account: game.coin_account(&challenger.pubkey()),
+            wager_vault: game.coin_account(&battle),
+            challenger_authority: challenger.pubkey(),
+            token_program: game.coin_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::ChallengeTrainer { wager }.data(),
+    }, &[challenger]).await.unwrap();
+    battle
+}
+
+async fn accept(game: &mut Game, opponent: &Keypair, battle: Pubkey) {
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::AcceptBattle {
+            battle,
+            game_state: pda(&[b"game_state"]),
+            poke_coin_mint: game.poke_coin_mint,
+            opponent_coin_account: game.coin_account(&opponent.pubkey()),
+            wager_vault: game.coin_account(&battle),
+            opponent: opponent.pubkey(),
+            token_program: game.coin_program,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::AcceptBattle {}.data(),
+    }, &[opponent]).await.unwrap();
+}
+
+async fn select(game: &mut Game, trainer: &Keypair, battle: Pubkey, pokemon_mint: Pubkey) {
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::SelectPokemon {
+            battle,
+            trainer: pda(&[b"trainer", trainer.pubkey().as_ref()]),
+            pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+            rental: None,
+            trainer_authority: trainer.pubkey(),
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::SelectPokemon { pokemon_mint }.data(),
+    }, &[trainer]).await.unwrap();
+}
+
+async fn use_move(game: &mut Game, trainer: &Keypair, battle: Pubkey, attacker: Pubkey, defender: Pubkey) {
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::UseMove {
+            battle,
+            attacker_pokemon: pda(&[b"pokemon", attacker.as_ref()]),
+            defender_pokemon: pda(&[b"pokemon", defender.as_ref()]),
+            move_def: pda(&[b"move", TACKLE.to_le_bytes().as_ref()]),
+            trainer: trainer.pubkey(),
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::UseMove { move_id: TACKLE }.data(),
+    }, &[trainer]).await.unwrap();
+}
+
+async fn claim_timeout(game: &mut Game, battle: Pubkey) -> Result<(), String> {
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::ClaimBattleTimeout { battle }.to_account_metas(None),
+        data: pokemon_game::instruction::ClaimBattleTimeout {}.data(),
+    }, &[]).await
+}
+
+async fn settle(game: &mut Game, battle: Pubkey, winner: Pubkey, loser: Pubkey, challenger: Pubkey) {
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::EndBattle {
+            battle,
+            game_state: pda(&[b"game_state"]),
+            winner_trainer: pda(&[b"trainer", winner.as_ref()]),
+            loser_trainer: pda(&[b"trainer", loser.as_ref()]),
+            winner_coin_account: game.coin_account(&winner),
+            wager_vault: game.coin_account(&battle),
+            treasury_coin_account: game.treasury,
+            poke_coin_mint: game.poke_coin_mint,
+            challenger_authority: challenger,
+            token_program: game.coin_program,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::EndBattleAndReward {}.data(),
+    }, &[]).await.unwrap();
+}
+
+/// Two trainers with a Pokemon each, in an accepted battle with both Pokemon sent out
+struct Duel {
+    game: Game,
+    challenger: Keypair,
+    opponent: Keypair,
+    challenger_pokemon: Pubkey,
+    opponent_pokemon: Pubkey,
+    battle: Pubkey,
+}
+
+async fn start_duel(wager: u64) -> Duel {
+    let mut game = Game::start(spl_token::ID).await;
+    game.register_move(TACKLE, PokemonType::Normal, 40).await;
+    let challenger = game.register_trainer("ash").await;
+    let opponent = game.register_trainer("gary").await;
+    let challenger_pokemon = game.catch_pokemon(&challenger, PIKACHU, "Pikachu").await;
+    let opponent_pokemon = game.catch_pokemon(&opponent, EEVEE, "Eevee").await;
+
+    let battle = challenge(&mut game, &challenger, opponent.pubkey(), wager).await;
+    accept(&mut game, &opponent, battle).await;
+    select(&mut game, &challenger, battle, challenger_pokemon).await;
+    select(&mut game, &opponent, battle, opponent_pokemon).await;
+
+    Duel { game, challenger, opponent, challenger_pokemon, opponent_pokemon, battle }
+}
+
+#[tokio::test]
+async fn winner_takes_wagers_and_capped_reward() {
+    let Duel { mut game, challenger, opponent, challenger_pokemon, opponent_pokemon, battle } =
+        start_duel(50 * COIN).await;
+    // The treasury could pay the full 10 PokeCoin reward, but the season budget is only 4
+    let oak = game.register_trainer("oak").await;
+    game.fund_treasury(&oak, 100 * COIN).await;
+    game.set_season_emission_cap(4 * COIN).await;
+    assert_eq!(game.token_balance(game.coin_account(&battle)).await, 100 * COIN);
+
+    // Trade blows until one Pokemon faints
+    loop {
+        let state = game.fetch::<Battle>(battle).await;
+        if state.state == BattleState::Finished {
+            break;
+        }
+        if state.current_turn % 2 == 0 {
+            use_move(&mut game, &challenger, battle, challenger_pokemon, opponent_pokemon).await;
+        } else {
+            use_move(&mut game, &opponent, battle, opponent_pokemon, challenger_pokemon).await;
+        }
+    }
+    let (winner, loser) = match game.fetch::<Battle>(battle).await.winner {
+        Some(0) => (challenger.pubkey(), opponent.pubkey()),
+        _ => (opponent.pubkey(), challenger.pubkey()),
+    };
+
+    settle(&mut game, battle, winner, loser, challenger.pubkey()).await;
+
+    assert_eq!(game.token_balance(game.coin_account(&winner)).await, 1000 * COIN + 50 * COIN + 4 * COIN);
+    assert_eq!(game.token_balance(game.coin_account(&loser)).await, 950 * COIN);
+    assert_eq!(game.token_balance(game.treasury).await, 96 * COIN);
+    assert_eq!(game.fetch::<GameState>(pda(&[b"game_state"])).await.season_emitted, 4 * COIN);
+    assert_eq!(game.trainer(&winner).await.battles_won, 1);
+    assert_eq!(game.trainer(&loser).await.battles_lost, 1);
+    assert!(!game.exists(battle).await);
+    assert!(!game.exists(game.coin_account(&battle)).await);
+}
+
+#[tokio::test]
+async fn stalled_battle_is_won_by_timeout() {
+    let Duel { mut game, challenger, opponent, battle, .. } = start_duel(50 * COIN).await;
+
+    // It's the challenger's turn, and they stop playing
+    assert_error(claim_timeout(&mut game, battle).await, ErrorCode::TurnNotTimedOut);
+    game.warp_seconds(BATTLE_TURN_TIMEOUT + 1).await;
+    claim_timeout(&mut game, battle).await.unwrap();
+
+    let state = game.fetch::<Battle>(battle).await;
+    assert!(state.state == BattleState::Finished);
+    assert_eq!(state.winner, Some(1));
+
+    settle(&mut game, battle, opponent.pubkey(), challenger.pubkey(), challenger.pubkey()).await;
+    assert_eq!(game.token_balance(game.coin_account(&opponent.pubkey())).await, 1050 * COIN);
+    assert_eq!(game.token_balance(game.coin_account(&challenger.pubkey())).await, 950 * COIN);
+}
+
+#[tokio::test]
+async fn pokemon_in_battle_cannot_be_released() {
+    let Duel { mut game, challenger, opponent, challenger_pokemon, battle, .. } = start_duel(0).await;
+    let oak = game.register_trainer("oak").await;
+    game.fund_treasury(&oak, 100 * COIN).await;
+
+    assert_error(
+        game.release_pokemon(&challenger, challenger_pokemon, None).await,
+        ErrorCode::PokemonInBattle,
+    );
+    assert_error(
+        game.release_pokemon(&challenger, challenger_pokemon, Some(battle)).await,
+        ErrorCode::PokemonInBattle,
+    );
+
+    // Once the battle is over and settled the Pokemon is free again
+    game.warp_seconds(BATTLE_TURN_TIMEOUT + 1).await;
+    claim_timeout(&mut game, battle).await.unwrap();
+    settle(&mut game, battle, opponent.pubkey(), challenger.pubkey(), challenger.pubkey()).await;
+    game.release_pokemon(&challenger, challenger_pokemon, Some(battle)).await.unwrap();
+}
@@ -0,0 +1,441 @@
+// Each test file uses only some of these helpers
+#![allow(dead_code)]
+
+use anchor_lang::system_program;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account;
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::extension::{ExtensionType, StateWithExtensions};
+use pokemon_game::{Ability, ErrorCode, Pokemon, PokemonData, PokemonType, Trainer};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::clock::Clock;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+
+pub const COIN: u64 = 1_000_000_000; // PokeCoin has 9 decimals
+pub const PIKACHU: u16 = 25;
+pub const EEVEE: u16 = 133;
+
+// Anchor's entrypoint wants accounts that live for 'info
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    pokemon_game::entry(program_id, accounts, data)
+}
+
+/// A started game whose PokeCoin mint is owned by `coin_program`. Pokemon NFTs always use
+/// Token-2022.
+pub struct Game {
+    pub context: ProgramTestContext,
+    pub coin_program: Pubkey,
+    pub transfer_fee_bps: u16,
+    pub poke_coin_mint: Pubkey,
+    pub treasury: Pubkey,
+    pub collection_mint: Pubkey,
+}
+
+pub fn assert_error(result: Result<(), String>, error: ErrorCode) {
+    let code = format!("custom program error: {:#x}", u32::from(error));
+    let message = result.expect_err("the instruction should have failed");
+    assert!(message.contains(&code), "expected {code}, got: {message}");
+}
+
+pub fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &pokemon_game::ID).0
+}
+
+pub fn pokemon_data(species_id: u16, name: &str) -> PokemonData {
+    PokemonData {
+        species_id,
+        name: name.to_string(),
+        base_hp: 55,
+        base_attack: 55,
+        base_defense: 50,
+        base_sp_attack: 45,
+        base_sp_defense: 65,
+        base_speed: 55,
+        types: vec![PokemonType::Normal],
+        moves: vec![1],
+    }
+}
+
+impl Game {
+    pub async fn send(&mut self, instruction: Instruction, signers: &[&Keypair]) -> Result<(), String> {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            &[ComputeBudgetInstruction::set_compute_unit_limit(1_400_000), instruction],
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.context.banks_client.process_transaction(transaction).await.map_err(|e| e.to_string())
+    }
+
+    pub async fn start(coin_program: Pubkey) -> Game {
+        Game::launch(coin_program, 0).await
+    }
+
+    /// A game whose PokeCoin is a Token-2022 mint charging `fee_bps` on every transfer
+    pub async fn start_with_transfer_fee(fee_bps: u16) -> Game {
+        Game::launch(spl_token_2022::ID, fee_bps).await
+    }
+
+    async fn launch(coin_program: Pubkey, transfer_fee_bps: u16) -> Game {
+        let mut program_test = ProgramTest::new("pokemon_game", pokemon_game::ID, processor!(process_instruction));
+        // The Token-2022 build bundled with program-test predates token groups, so run the
+        // processor anchor-spl depends on instead
+        program_test.add_program(
+            "spl_token_2022",
+            spl_token_2022::ID,
+            processor!(spl_token_2022::processor::Processor::process),
+        );
+        let context = program_test.start_with_context().await;
+        let authority = context.payer.pubkey();
+        let game_state = pda(&[b"game_state"]);
+
+        let poke_coin_mint = Keypair::new();
+        let collection_mint = Keypair::new();
+        let mut game = Game {
+            context,
+            coin_program,
+            transfer_fee_bps,
+            poke_coin_mint: poke_coin_mint.pubkey(),
+            treasury: get_associated_token_address_with_program_id(&game_state, &poke_coin_mint.pubkey(), &coin_program),
+            collection_mint: collection_mint.pubkey(),
+        };
+
+        // The game state mints PokeCoin, and the treasury is its token account
+        let extensions = if transfer_fee_bps > 0 { vec![ExtensionType::TransferFeeConfig] } else { vec![] };
+        let mint_len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&extensions).unwrap();
+        let rent = game.context.banks_client.get_rent().await.unwrap();
+        game.send(system_instruction::create_account(
+            &authority,
+            &game.poke_coin_mint,
+            rent.minimum_balance(mint_len),
+            mint_len as u64,
+            &coin_program,
+        ), &[&poke_coin_mint]).await.unwrap();
+        if transfer_fee_bps > 0 {
+            game.send(spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config(
+                &coin_program,
+                &game.poke_coin_mint,
+                None,
+                None,
+                transfer_fee_bps,
+                u64::MAX,
+            ).unwrap(), &[]).await.unwrap();
+        }
+        game.send(spl_token_2022::instruction::initialize_mint2(
+            &coin_program,
+            &game.poke_coin_mint,
+            &game_state,
+            None,
+            9,
+        ).unwrap(), &[]).await.unwrap();
+        game.send(create_associated_token_account(&authority, &game_state, &game.poke_coin_mint, &coin_program), &[])
+            .await
+            .unwrap();
+
+        game.send(Instruction {
+            program_id: pokemon_game::ID,
+            accounts: pokemon_game::accounts::Initialize {
+                game_state,
+                authority,
+                poke_coin_mint: game.poke_coin_mint,
+                treasury: game.treasury,
+                collection_mint: game.collection_mint,
+                collection_token_account: get_associated_token_address_with_program_id(
+                    &game_state,
+                    &game.collection_mint,
+                    &spl_token_2022::ID,
+                ),
+                token_program: spl_token_2022::ID,
+                coin_token_program: coin_program,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: pokemon_game::instruction::Initialize {
+                royalty_bps: 500,
+                base_uri: "https://pokemon.example/api".to_string(),
+            }.data(),
+        }, &[&collection_mint]).await.unwrap();
+
+        for (species_id, name) in [(PIKACHU, "Pikachu"), (EEVEE, "Eevee")] {
+            game.send(Instruction {
+                program_id: pokemon_game::ID,
+                accounts: pokemon_game::accounts::RegisterSpecies {
+                    species: pda(&[b"species", species_id.to_le_bytes().as_ref()]),
+                    game_state,
+                    authority,
+                    system_program: system_program::ID,
+                }.to_account_metas(None),
+                data: pokemon_game::instruction::RegisterSpecies {
+                    data: pokemon_data(species_id, name),
+                    catch_rate: 190,
+                    abilities: vec![Ability::Static],
+                    trade_evolution: None,
+                }.data(),
+            }, &[]).await.unwrap();
+        }
+
+        game
+    }
+
+    /// What the PokeCoin mint withholds from a transfer of `amount`, rounded up like Token-2022
+    pub fn transfer_fee(&self, amount: u64) -> u64 {
+        (amount * self.transfer_fee_bps as u64).div_ceil(10_000)
+    }
+
+    pub fn coin_account(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, &self.poke_coin_mint, &self.coin_program)
+    }
+
+    /// A funded wallet with a registered trainer and its 1000 starting PokeCoin
+    pub async fn register_trainer(&mut self, username: &str) -> Keypair {
+        let wallet = Keypair::new();
+        let payer = self.context.payer.pubkey();
+        self.send(system_instruction::transfer(&payer, &wallet.pubkey(), 10_000_000_000), &[]).await.unwrap();
+
+        self.send(Instruction {
+            program_id: pokemon_game::ID,
+            accounts: pokemon_game::accounts::RegisterTrainer {
+                trainer: pda(&[b"trainer", wallet.pubkey().as_ref()]),
+                game_state: pda(&[b"game_state"]),
+                authority: wallet.pubkey(),
+                poke_coin_mint: self.poke_coin_mint,
+                trainer_coin_account: self.coin_account(&wallet.pubkey()),
+                token_program: self.coin_program,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: pokemon_game::instruction::RegisterTrainer { username: username.to_string() }.data(),
+        }, &[&wallet]).await.unwrap();
+
+        wallet
+    }
+
+    /// Gift a Pokemon to a trainer and return its mint
+    pub async fn catch_pokemon(&mut self, trainer: &Keypair, species_id: u16, name: &str) -> Pubkey {
+        let pokemon_mint = Keypair::new();
+        let authority = self.context.payer.pubkey();
+        self.send(Instruction {
+            program_id: pokemon_game::ID,
+            accounts: pokemon_game::accounts::CatchPokemon {
+                trainer: pda(&[b"trainer", trainer.pubkey().as_ref()]),
+                game_state: pda(&[b"game_state"]),
+                pokemon: pda(&[b"pokemon", pokemon_mint.pubkey().as_ref()]),
+                pokemon_mint: pokemon_mint.pubkey(),
+                trainer_pokemon_account: self.pokemon_account(&trainer.pubkey(), &pokemon_mint.pubkey()),
+                collection_mint: self.collection_mint,
+                species: pda(&[b"species", species_id.to_le_bytes().as_ref()]),
+                payer: authority,
+                trainer_authority: trainer.pubkey(),
+                token_program: spl_token_2022::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: pokemon_game::instruction::CatchPokemon { pokemon_data: pokemon_data(species_id, name) }.data(),
+        }, &[&pokemon_mint, trainer]).await.unwrap();
+
+        pokemon_mint.pubkey()
+    }
+
+    pub fn pokemon_account(&self, owner: &Pubkey, pokemon_mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, pokemon_mint, &spl_token_2022::ID)
+    }
+
+    pub async fn token_balance(&mut self, address: Pubkey) -> u64 {
+        let account = self.context.banks_client.get_account(address).await.unwrap().unwrap();
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).unwrap().base.amount
+    }
+
+    pub async fn fetch<T: AccountDeserialize>(&mut self, address: Pubkey) -> T {
+        let account = self.context.banks_client.get_account(address).await.unwrap().unwrap();
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub async fn exists(&mut self, address: Pubkey) -> bool {
+        self.context.banks_client.get_account(address).await.unwrap().is_some()
+    }
+
+    pub async fn pokemon(&mut self, pokemon_mint: &Pubkey) -> Pokemon {
+        self.fetch(pda(&[b"pokemon", pokemon_mint.as_ref()])).await
+    }
+
+    pub async fn trainer(&mut self, wallet: &Pubkey) -> Trainer {
+        self.fetch(pda(&[b"trainer", wallet.as_ref()])).await
+    }
+
+    pub async fn now(&mut self) -> i64 {
+        self.context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+    }
+
+    /// Move the clock forward without producing slots
+    pub async fn warp_seconds(&mut self, seconds: i64) {
+        let mut clock = self.context.banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    pub async fn warp_slots(&mut self, slots: u64) {
+        let slot = self.context.banks_client.get_root_slot().await.unwrap();
+        self.context.warp_to_slot(slot + slots).unwrap();
+    }
+
+    pub async fn register_move(&mut self, move_id: u16, move_type: PokemonType, power: u16) {
+        let authority = self.context.payer.pubkey();
+        self.send(Instruction {
+            program_id: pokemon_game::ID,
+            accounts: pokemon_game::accounts::RegisterMove {
+                move_def: pda(&[b"move", move_id.to_le_bytes().as_ref()]),
+                game_state: pda(&[b"game_state"]),
+                authority,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: pokemon_game::instruction::RegisterMove { move_id, move_type, power, effect: None }.data(),
+        }, &[]).await.unwrap();
+    }
+
+    /// Set the season's PokeCoin budget for battle and gym rewards
+    pub async fn set_season_emission_cap(&mut self, emission_cap: u64) {
+        let authority = self.context.payer.pubkey();
+        self.send(Instruction {
+            program_id: pokemon_game::ID,
+            accounts: pokemon_game::accounts::UpdateGameConfig {
+                game_state: pda(&[b"game_state"]),
+                authority,
+            }.to_account_metas(None),
+            data: pokemon_game::instruction::SetSeasonEmissionCap { emission_cap }.data(),
+        }, &[]).await.unwrap();
+    }
+
+    /// Move some of a trainer's PokeCoin into the treasury, which pays rewards and refunds
+    pub async fn fund_treasury(&mut self, from: &Keypair, amount: u64) {
+        let instruction = spl_token_2022::instruction::transfer_checked(
+            &self.coin_program,
+            &self.coin_account(&from.pubkey()),
+            &self.poke_coin_mint,
+            &self.treasury,
+            &from.pubkey(),
+            &[],
+            amount,
+            9,
+        ).unwrap();
+        self.send(instruction, &[from]).await.unwrap();
+    }
+
+    /// Burn a Pokemon for its PokeCoin value; `battle` is the PvP battle it was last sent out in
+    pub async fn release_pokemon(&mut self, owner: &Keypair, pokemon_mint: Pubkey, battle: Option<Pubkey>) -> Result<(), String> {
+        self.send(Instruction {
+            program_id: pokemon_game::ID,
+            accounts: pokemon_game::accounts::ReleasePokemon {
+                pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+                pokemon_mint,
+                owner_pokemon_account: self.pokemon_account(&owner.pubkey(), &pokemon_mint),
+                trainer: pda(&[b"trainer", owner.pubkey().as_ref()]),
+                game_state: pda(&[b"game_state"]),
+                poke_coin_mint: self.poke_coin_mint,
+                treasury_coin_account: self.treasury,
+                owner_coin_account: self.coin_account(&owner.pubkey()),
+                battle,
+                owner: owner.pubkey(),
+                token_program: spl_token_2022::ID,
+                coin_token_program: self.coin_program,
+            }.to_account_metas(None),
+            data: pokemon_game::instruction::ReleasePokemon {}.data(),
+        }, &[owner]).await
+    }
+
+    /// Put a Pokemon up for auction; bids go to the auction's PokeCoin vault
+    pub async fn create_auction(&mut self, seller: &Keypair, pokemon_mint: Pubkey, reserve_price: u64, duration: i64) {
+        let auction = pda(&[b"auction", pokemon_mint.as_ref()]);
+        self.send(Instruction {
+            program_id: pokemon_game::ID,
+            accounts: pokemon_game::accounts::CreateAuction {
+                auction,
+                pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+                pokemon_mint,
+                game_state: pda(&[b"game_state"]),
+                poke_coin_mint: self.poke_coin_mint,
+                seller_pokemon_account: self.pokemon_account(&seller.pubkey(), &pokemon_mint),
+                escrow_pokemon_account: self.pokemon_account(&auction, &pokemon_mint),
+                bid_vault: self.coin_account(&auction),
+                seller: seller.pubkey(),
+                token_program: spl_token_2022::ID,
+                coin_token_program: self.coin_program,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: pokemon_game::instruction::CreateAuction {
+                reserve_price,
+                min_bid_increment: 10 * COIN,
+                duration,
+            }.data(),
+        }, &[seller]).await.unwrap();
+    }
+
+    /// Bid on an auction, refunding `previous_bidder` if there is one
+    pub async fn place_bid(
+        &mut self,
+        bidder: &Keypair,
+        pokemon_mint: Pubkey,
+        amount: u64,
+        previous_bidder: Option<Pubkey>,
+    ) -> Result<(), String> {
+        let auction = pda(&[b"auction", pokemon_mint.as_ref()]);
+        self.send(Instruction {
+            program_id: pokemon_game::ID,
+            accounts: pokemon_game::accounts::PlaceBid {
+                auction,
+                game_state: pda(&[b"game_state"]),
+                poke_coin_mint: self.poke_coin_mint,
+                bid_vault: self.coin_account(&auction),
+                bidder_coin_account: self.coin_account(&bidder.pubkey()),
+                previous_bidder_coin_account: previous_bidder.map(|bidder| self.coin_account(&bidder)),
+                bidder_trainer: pda(&[b"trainer", bidder.pubkey().as_ref()]),
+                bidder: bidder.pubkey(),
+                token_program: self.coin_program,
+            }.to_account_metas(None),
+            data: pokemon_game::instruction::PlaceBid { amount }.data(),
+        }, &[bidder]).await
+    }
+
+    /// Settle an auction, sending the Pokemon to `recipient` (the winner, or the seller without bids)
+    pub async fn settle_auction(&mut self, pokemon_mint: Pubkey, seller: Pubkey, recipient: Pubkey) -> Result<(), String> {
+        let auction = pda(&[b"auction", pokemon_mint.as_ref()]);
+        let payer = self.context.payer.pubkey();
+        self.send(Instruction {
+            program_id: pokemon_game::ID,
+            accounts: pokemon_game::accounts::SettleAuction {
+                auction,
+                pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+                pokemon_mint,
+                game_state: pda(&[b"game_state"]),
+                escrow_pokemon_account: self.pokemon_account(&auction, &pokemon_mint),
+                poke_coin_mint: self.poke_coin_mint,
+                bid_vault: self.coin_account(&auction),
+                recipient,
+                recipient_pokemon_account: self.pokemon_account(&recipient, &pokemon_mint),
+                recipient_trainer: pda(&[b"trainer", recipient.as_ref()]),
+                seller_trainer: pda(&[b"trainer", seller.as_ref()]),
+                seller_coin_account: self.coin_account(&seller),
+                treasury_coin_account: self.treasury,
+                seller,
+                payer,
+                token_program: spl_token_2022::ID,
+                coin_token_program: self.coin_program,
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: pokemon_game::instruction::SettleAuction {}.data(),
+        }, &[]).await
+    }
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use common::{assert_error, pda, Game, COIN, EEVEE, PIKACHU};
+use pokemon_game::{BattleState, ErrorCode, NpcPokemon, PokemonType, PveBattle};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const TACKLE: u16 = 1;
+const GYM: u16 = 1;
+const BOULDER_BADGE: u8 = 1;
+
+async fn create_gym(game: &mut Game, leader_team: Vec<NpcPokemon>, level_cap: u8, reward: u64) {
+    let authority = game.context.payer.pubkey();
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::CreateGym {
+            gym: pda(&[b"gym", GYM.to_le_bytes().as_ref()]),
+            badge: pda(&[b"badge", [BOULDER_BADGE].as_ref()]),
+            game_state: pda(&[b"game_state"]),
+            authority,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::CreateGym {
+            gym_id: GYM,
+            leader_team,
+            level_cap,
+            type_specialty: PokemonType::Normal,
+            badge_id: BOULDER_BADGE,
+            reward,
+        }.data(),
+    }, &[]).await.unwrap();
+}
+
+async fn challenge_gym(game: &mut Game, owner: &Keypair) -> Result<(), String> {
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::ChallengeGym {
+            pve_battle: pda(&[b"pve_battle", owner.pubkey().as_ref()]),
+            gym: pda(&[b"gym", GYM.to_le_bytes().as_ref()]),
+            trainer: pda(&[b"trainer", owner.pubkey().as_ref()]),
+            owner: owner.pubkey(),
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::ChallengeGym { gym_id: GYM }.data(),
+    }, &[owner]).await
+}
+
+/// Attack with Tackle; the leader's Eevee answers with its own Tackle
+async fn pve_move(game: &mut Game, owner: &Keypair, pokemon_mint: Pubkey) {
+    let tackle = pda(&[b"move", TACKLE.to_le_bytes().as_ref()]);
+    let mut accounts = pokemon_game::accounts::PveMove {
+        pve_battle: pda(&[b"pve_battle", owner.pubkey().as_ref()]),
+        pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+        opponent_species: pda(&[b"species", EEVEE.to_le_bytes().as_ref()]),
+        move_def: tackle,
+        owner: owner.pubkey(),
+    }.to_account_metas(None);
+    accounts.push(AccountMeta::new_readonly(tackle, false));
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts,
+        data: pokemon_game::instruction::PveMove { move_id: TACKLE }.data(),
+    }, &[owner]).await.unwrap();
+}
+
+async fn claim_badge(game: &mut Game, owner: &Keypair) -> Result<(), String> {
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::ClaimGymBadge {
+            pve_battle: pda(&[b"pve_battle", owner.pubkey().as_ref()]),
+            gym: pda(&[b"gym", GYM.to_le_bytes().as_ref()]),
+            trainer: pda(&[b"trainer", owner.pubkey().as_ref()]),
+            game_state: pda(&[b"game_state"]),
+            poke_coin_mint: game.poke_coin_mint,
+            treasury_coin_account: game.treasury,
+            owner_coin_account: game.coin_account(&owner.pubkey()),
+            owner: owner.pubkey(),
+            token_program: game.coin_program,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::ClaimGymBadge {}.data(),
+    }, &[owner]).await
+}
+
+#[tokio::test]
+async fn gym_badge_is_earned_once_with_capped_reward() {
+    let mut game = Game::start(spl_token::ID).await;
+    game.register_move(TACKLE, PokemonType::Normal, 40).await;
+    let oak = game.register_trainer("oak").await;
+    let ash = game.register_trainer("ash").await;
+    let pikachu = game.catch_pokemon(&ash, PIKACHU, "Pikachu").await;
+    let leader_team = vec![NpcPokemon { species_id: EEVEE, level: 1, moves: vec![TACKLE] }];
+    create_gym(&mut game, leader_team, 50, 10).await;
+    // The gym promises 10 PokeCoin, but the season budget is only 4
+    game.fund_treasury(&oak, 100 * COIN).await;
+    game.set_season_emission_cap(4 * COIN).await;
+
+    challenge_gym(&mut game, &ash).await.unwrap();
+    let pve_battle = pda(&[b"pve_battle", ash.pubkey().as_ref()]);
+    assert_error(claim_badge(&mut game, &ash).await, ErrorCode::BattleNotWon);
+    for _ in 0..10 {
+        if game.fetch::<PveBattle>(pve_battle).await.state == BattleState::Finished {
+            break;
+        }
+        pve_move(&mut game, &ash, pikachu).await;
+    }
+    assert!(game.fetch::<PveBattle>(pve_battle).await.won);
+
+    claim_badge(&mut game, &ash).await.unwrap();
+    assert_eq!(game.token_balance(game.coin_account(&ash.pubkey())).await, 1000 * COIN + 4 * COIN);
+    assert_eq!(game.token_balance(game.treasury).await, 96 * COIN);
+    assert_eq!(game.trainer(&ash.pubkey()).await.badges, vec![BOULDER_BADGE]);
+    assert!(!game.exists(pve_battle).await);
+
+    // The badge can't be earned again
+    assert_error(challenge_gym(&mut game, &ash).await, ErrorCode::BadgeAlreadyEarned);
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use common::{assert_error, pda, Game, COIN};
+use pokemon_game::{ErrorCode, Inventory, Item, ItemKind};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const POTION: u16 = 1;
+
+async fn create_item(game: &mut Game, item_id: u16, price: u64, stock: Option<u32>) {
+    let authority = game.context.payer.pubkey();
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::CreateItem {
+            item: pda(&[b"item", item_id.to_le_bytes().as_ref()]),
+            game_state: pda(&[b"game_state"]),
+            authority,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::CreateItem {
+            item_id,
+            name: "Potion".to_string(),
+            kind: ItemKind::Potion,
+            effect: 20,
+            price,
+            stock,
+        }.data(),
+    }, &[]).await.unwrap();
+}
+
+async fn buy_item(game: &mut Game, buyer: &Keypair, item_id: u16, quantity: u32) -> Result<(), String> {
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::BuyItem {
+            item: pda(&[b"item", item_id.to_le_bytes().as_ref()]),
+            inventory: inventory(buyer.pubkey()),
+            game_state: pda(&[b"game_state"]),
+            poke_coin_mint: game.poke_coin_mint,
+            buyer_coin_account: game.coin_account(&buyer.pubkey()),
+            treasury_coin_account: game.treasury,
+            buyer: buyer.pubkey(),
+            token_program: game.coin_program,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::BuyItem { quantity }.data(),
+    }, &[buyer]).await
+}
+
+fn inventory(owner: Pubkey) -> Pubkey {
+    pda(&[b"inventory", owner.as_ref()])
+}
+
+async fn quantity(game: &mut Game, owner: Pubkey, item_id: u16) -> u32 {
+    let inventory = game.fetch::<Inventory>(inventory(owner)).await;
+    inventory.items.iter().find(|stack| stack.item_id == item_id).map_or(0, |stack| stack.quantity)
+}
+
+#[tokio::test]
+async fn buying_pays_the_treasury_and_draws_down_stock() {
+    let mut game = Game::start(spl_token::ID).await;
+    let buyer = game.register_trainer("ash").await;
+    create_item(&mut game, POTION, 5 * COIN, Some(3)).await;
+
+    assert_error(buy_item(&mut game, &buyer, POTION, 0).await, ErrorCode::InvalidQuantity);
+    buy_item(&mut game, &buyer, POTION, 2).await.unwrap();
+    assert_eq!(game.token_balance(game.coin_account(&buyer.pubkey())).await, 1000 * COIN - 10 * COIN);
+    assert_eq!(game.token_balance(game.treasury).await, 10 * COIN);
+    assert_eq!(quantity(&mut game, buyer.pubkey(), POTION).await, 2);
+    assert_eq!(game.fetch::<Item>(pda(&[b"item", POTION.to_le_bytes().as_ref()])).await.stock, Some(1));
+
+    // Only one left, and a failed purchase charges nothing
+    assert_error(buy_item(&mut game, &buyer, POTION, 2).await, ErrorCode::ItemOutOfStock);
+    assert_eq!(game.token_balance(game.treasury).await, 10 * COIN);
+
+    // A second purchase stacks onto the existing inventory entry
+    buy_item(&mut game, &buyer, POTION, 1).await.unwrap();
+    assert_eq!(quantity(&mut game, buyer.pubkey(), POTION).await, 3);
+    assert_eq!(game.fetch::<Inventory>(inventory(buyer.pubkey())).await.items.len(), 1);
+    assert_eq!(game.fetch::<Item>(pda(&[b"item", POTION.to_le_bytes().as_ref()])).await.stock, Some(0));
+}
+
+#[tokio::test]
+async fn unlimited_items_sell_for_token_2022_coin() {
+    let mut game = Game::start(spl_token_2022::ID).await;
+    let buyer = game.register_trainer("ash").await;
+    create_item(&mut game, POTION, 5 * COIN, None).await;
+
+    buy_item(&mut game, &buyer, POTION, 50).await.unwrap();
+    assert_eq!(game.token_balance(game.coin_account(&buyer.pubkey())).await, 1000 * COIN - 250 * COIN);
+    assert_eq!(game.token_balance(game.treasury).await, 250 * COIN);
+    assert_eq!(quantity(&mut game, buyer.pubkey(), POTION).await, 50);
+    assert_eq!(game.fetch::<Item>(pda(&[b"item", POTION.to_le_bytes().as_ref()])).await.stock, None);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use common::{pda, Game, COIN, EEVEE, PIKACHU};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn release_pokemon_spl_token() {
+    release_pokemon(spl_token::ID).await;
+}
+
+#[tokio::test]
+async fn release_pokemon_token_2022() {
+    release_pokemon(spl_token_2022::ID).await;
+}
+
+async fn release_pokemon(coin_program: Pubkey) {
+    let mut game = Game::start(coin_program).await;
+    let ash = game.register_trainer("ash").await;
+    let oak = game.register_trainer("oak").await;
+    game.fund_treasury(&oak, 500 * COIN).await;
+    let pokemon_mint = game.catch_pokemon(&ash, PIKACHU, "Pikachu").await;
+    let kept_mint = game.catch_pokemon(&ash, EEVEE, "Eevee").await;
+
+    // Worth level x base stat total / 100 whole PokeCoins
+    let pokemon = game.pokemon(&pokemon_mint).await;
+    let base_stat_total: u64 = pokemon.base_stats.iter().map(|&stat| stat as u64).sum();
+    let reward = pokemon.level as u64 * base_stat_total / 100 * COIN;
+    assert!(reward > 0);
+
+    game.release_pokemon(&ash, pokemon_mint, None).await.unwrap();
+
+    assert_eq!(game.token_balance(game.coin_account(&ash.pubkey())).await, 1000 * COIN + reward);
+    assert_eq!(game.token_balance(game.treasury).await, 500 * COIN - reward);
+
+    // The NFT, its mint and the Pokemon account are gone, and the trainer no longer lists it
+    assert!(!game.exists(pokemon_mint).await);
+    assert!(!game.exists(game.pokemon_account(&ash.pubkey(), &pokemon_mint)).await);
+    assert!(!game.exists(pda(&[b"pokemon", pokemon_mint.as_ref()])).await);
+    let trainer = game.trainer(&ash.pubkey()).await;
+    assert_eq!(trainer.pokemon_team, vec![kept_mint]);
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use common::{assert_error, pda, Game, COIN, PIKACHU};
+use pokemon_game::{ErrorCode, PokemonStatus, Rental};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const DAY: i64 = 24 * 60 * 60;
+
+async fn offer(game: &mut Game, owner: &Keypair, pokemon_mint: Pubkey, fee: u64, duration: i64) {
+    let rental = pda(&[b"rental", pokemon_mint.as_ref()]);
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::OfferRental {
+            rental,
+            pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+            pokemon_mint,
+            owner_pokemon_account: game.pokemon_account(&owner.pubkey(), &pokemon_mint),
+            escrow_pokemon_account: game.pokemon_account(&rental, &pokemon_mint),
+            battle: None,
+            owner: owner.pubkey(),
+            token_program: spl_token_2022::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::OfferRental { fee, duration }.data(),
+    }, &[owner]).await.unwrap();
+}
+
+async fn rent(game: &mut Game, borrower: &Keypair, owner: Pubkey, pokemon_mint: Pubkey) -> Result<(), String> {
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::RentPokemon {
+            rental: pda(&[b"rental", pokemon_mint.as_ref()]),
+            game_state: pda(&[b"game_state"]),
+            poke_coin_mint: game.poke_coin_mint,
+            borrower_coin_account: game.coin_account(&borrower.pubkey()),
+            owner_coin_account: game.coin_account(&owner),
+            treasury_coin_account: game.treasury,
+            borrower: borrower.pubkey(),
+            token_program: game.coin_program,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::RentPokemon {}.data(),
+    }, &[borrower]).await
+}
+
+async fn end_rental(game: &mut Game, owner: &Keypair, pokemon_mint: Pubkey) -> Result<(), String> {
+    let rental = pda(&[b"rental", pokemon_mint.as_ref()]);
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::EndRental {
+            rental,
+            pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+            pokemon_mint,
+            escrow_pokemon_account: game.pokemon_account(&rental, &pokemon_mint),
+            owner_pokemon_account: game.pokemon_account(&owner.pubkey(), &pokemon_mint),
+            owner: owner.pubkey(),
+            token_program: spl_token_2022::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::EndRental {}.data(),
+    }, &[owner]).await
+}
+
+#[tokio::test]
+async fn rental_pays_owner_and_returns_pokemon() {
+    let mut game = Game::start(spl_token::ID).await;
+    let owner = game.register_trainer("misty").await;
+    let borrower = game.register_trainer("ash").await;
+    let late = game.register_trainer("gary").await;
+    let pokemon_mint = game.catch_pokemon(&owner, PIKACHU, "Pikachu").await;
+    let fee = 100 * COIN;
+    offer(&mut game, &owner, pokemon_mint, fee, DAY).await;
+
+    let rental = pda(&[b"rental", pokemon_mint.as_ref()]);
+    assert_eq!(game.token_balance(game.pokemon_account(&rental, &pokemon_mint)).await, 1);
+    assert!(game.pokemon(&pokemon_mint).await.status == PokemonStatus::Lent);
+    assert_error(rent(&mut game, &owner, owner.pubkey(), pokemon_mint).await, ErrorCode::CannotRentOwnPokemon);
+
+    // The owner is paid up front, minus the 2.5% protocol fee
+    rent(&mut game, &borrower, owner.pubkey(), pokemon_mint).await.unwrap();
+    let protocol_fee = fee * 250 / 10_000;
+    assert_eq!(game.token_balance(game.coin_account(&borrower.pubkey())).await, 1000 * COIN - fee);
+    assert_eq!(game.token_balance(game.coin_account(&owner.pubkey())).await, 1000 * COIN + fee - protocol_fee);
+    assert_eq!(game.token_balance(game.treasury).await, protocol_fee);
+    let now = game.now().await;
+    let state = game.fetch::<Rental>(rental).await;
+    assert_eq!(state.borrower, Some(borrower.pubkey()));
+    assert_eq!(state.expires_at, now + DAY);
+
+    assert_error(rent(&mut game, &late, owner.pubkey(), pokemon_mint).await, ErrorCode::AlreadyRented);
+    assert_error(end_rental(&mut game, &owner, pokemon_mint).await, ErrorCode::RentalNotExpired);
+
+    game.warp_seconds(DAY).await;
+    end_rental(&mut game, &owner, pokemon_mint).await.unwrap();
+    assert_eq!(game.token_balance(game.pokemon_account(&owner.pubkey(), &pokemon_mint)).await, 1);
+    assert!(game.pokemon(&pokemon_mint).await.status == PokemonStatus::Active);
+    assert!(!game.exists(rental).await);
+}
+
+#[tokio::test]
+async fn unrented_offer_can_be_withdrawn() {
+    let mut game = Game::start(spl_token::ID).await;
+    let owner = game.register_trainer("misty").await;
+    let pokemon_mint = game.catch_pokemon(&owner, PIKACHU, "Pikachu").await;
+    offer(&mut game, &owner, pokemon_mint, 100 * COIN, DAY).await;
+
+    end_rental(&mut game, &owner, pokemon_mint).await.unwrap();
+    assert_eq!(game.token_balance(game.pokemon_account(&owner.pubkey(), &pokemon_mint)).await, 1);
+    assert_eq!(game.token_balance(game.coin_account(&owner.pubkey())).await, 1000 * COIN);
+    assert!(game.pokemon(&pokemon_mint).await.status == PokemonStatus::Active);
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use common::{pda, Game, COIN, PIKACHU};
+use pokemon_game::{PokemonStatus, Stake, SLOTS_PER_DAY};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+async fn stake(game: &mut Game, owner: &Keypair, pokemon_mint: Pubkey) {
+    let stake = pda(&[b"stake", pokemon_mint.as_ref()]);
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::StakePokemon {
+            stake,
+            pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+            pokemon_mint,
+            owner_pokemon_account: game.pokemon_account(&owner.pubkey(), &pokemon_mint),
+            stake_vault: game.pokemon_account(&stake, &pokemon_mint),
+            battle: None,
+            owner: owner.pubkey(),
+            token_program: spl_token_2022::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::StakePokemon {}.data(),
+    }, &[owner]).await.unwrap();
+}
+
+async fn claim(game: &mut Game, owner: &Keypair, pokemon_mint: Pubkey) {
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::ClaimStakingRewards {
+            stake: pda(&[b"stake", pokemon_mint.as_ref()]),
+            pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+            game_state: pda(&[b"game_state"]),
+            poke_coin_mint: game.poke_coin_mint,
+            treasury_coin_account: game.treasury,
+            owner_coin_account: game.coin_account(&owner.pubkey()),
+            owner: owner.pubkey(),
+            coin_token_program: game.coin_program,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::ClaimStakingRewards {}.data(),
+    }, &[owner]).await.unwrap();
+}
+
+async fn unstake(game: &mut Game, owner: &Keypair, pokemon_mint: Pubkey) {
+    let stake = pda(&[b"stake", pokemon_mint.as_ref()]);
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::UnstakePokemon {
+            stake,
+            pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+            pokemon_mint,
+            stake_vault: game.pokemon_account(&stake, &pokemon_mint),
+            owner_pokemon_account: game.pokemon_account(&owner.pubkey(), &pokemon_mint),
+            game_state: pda(&[b"game_state"]),
+            poke_coin_mint: game.poke_coin_mint,
+            treasury_coin_account: game.treasury,
+            owner_coin_account: game.coin_account(&owner.pubkey()),
+            owner: owner.pubkey(),
+            token_program: spl_token_2022::ID,
+            coin_token_program: game.coin_program,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::UnstakePokemon {}.data(),
+    }, &[owner]).await.unwrap();
+}
+
+/// What a Pokemon earns per day staked: its release value in whole PokeCoins
+async fn value_per_day(game: &mut Game, pokemon_mint: Pubkey) -> u64 {
+    let pokemon = game.pokemon(&pokemon_mint).await;
+    let base_stat_total: u64 = pokemon.base_stats.iter().map(|&stat| stat as u64).sum();
+    pokemon.level as u64 * base_stat_total / 100
+}
+
+#[tokio::test]
+async fn staked_pokemon_accrues_rewards_per_slot() {
+    let mut game = Game::start(spl_token::ID).await;
+    let ash = game.register_trainer("ash").await;
+    let oak = game.register_trainer("oak").await;
+    game.fund_treasury(&oak, 500 * COIN).await;
+    let pokemon_mint = game.catch_pokemon(&ash, PIKACHU, "Pikachu").await;
+
+    stake(&mut game, &ash, pokemon_mint).await;
+    let stake_account = pda(&[b"stake", pokemon_mint.as_ref()]);
+    assert_eq!(game.token_balance(game.pokemon_account(&stake_account, &pokemon_mint)).await, 1);
+    assert!(game.pokemon(&pokemon_mint).await.status == PokemonStatus::Staked);
+
+    let staked_slot = game.fetch::<Stake>(stake_account).await.last_claim_slot;
+    game.warp_slots(SLOTS_PER_DAY / 2).await;
+    claim(&mut game, &ash, pokemon_mint).await;
+
+    // Rewards accrue pro rata per slot and the claim resets the clock
+    let claimed_slot = game.fetch::<Stake>(stake_account).await.last_claim_slot;
+    let reward = value_per_day(&mut game, pokemon_mint).await * COIN * (claimed_slot - staked_slot) / SLOTS_PER_DAY;
+    assert!(reward > 0);
+    assert_eq!(game.token_balance(game.coin_account(&ash.pubkey())).await, 1000 * COIN + reward);
+    assert_eq!(game.token_balance(game.treasury).await, 500 * COIN - reward);
+    assert!(game.pokemon(&pokemon_mint).await.status == PokemonStatus::Staked);
+}
+
+#[tokio::test]
+async fn unstake_pays_what_the_treasury_can_cover() {
+    let mut game = Game::start(spl_token::ID).await;
+    let ash = game.register_trainer("ash").await;
+    let oak = game.register_trainer("oak").await;
+    game.fund_treasury(&oak, COIN).await;
+    let pokemon_mint = game.catch_pokemon(&ash, PIKACHU, "Pikachu").await;
+    assert!(value_per_day(&mut game, pokemon_mint).await > 1);
+
+    stake(&mut game, &ash, pokemon_mint).await;
+    game.warp_slots(SLOTS_PER_DAY).await;
+    unstake(&mut game, &ash, pokemon_mint).await;
+
+    // A day's rewards are worth more than the treasury holds, so it pays out what it has
+    assert_eq!(game.token_balance(game.coin_account(&ash.pubkey())).await, 1001 * COIN);
+    assert_eq!(game.token_balance(game.treasury).await, 0);
+
+    let stake_account = pda(&[b"stake", pokemon_mint.as_ref()]);
+    assert_eq!(game.token_balance(game.pokemon_account(&ash.pubkey(), &pokemon_mint)).await, 1);
+    assert!(!game.exists(stake_account).await);
+    assert!(!game.exists(game.pokemon_account(&stake_account, &pokemon_mint)).await);
+    assert!(game.pokemon(&pokemon_mint).await.status == PokemonStatus::Active);
+}
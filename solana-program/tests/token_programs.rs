@@ -0,0 +1,246 @@
+mod common;
+
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::extension::StateWithExtensions;
+use common::{pda, Game, COIN, EEVEE, PIKACHU};
+use pokemon_game::PokemonStatus;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn catch_pokemon_spl_token() {
+    catch_pokemon(spl_token::ID).await;
+}
+
+#[tokio::test]
+async fn catch_pokemon_token_2022() {
+    catch_pokemon(spl_token_2022::ID).await;
+}
+
+async fn catch_pokemon(coin_program: Pubkey) {
+    let mut game = Game::start(coin_program).await;
+    let ash = game.register_trainer("ash").await;
+    assert_eq!(game.token_balance(game.coin_account(&ash.pubkey())).await, 1000 * COIN);
+
+    let pokemon_mint = game.catch_pokemon(&ash, PIKACHU, "Pikachu").await;
+
+    let pokemon = game.pokemon(&pokemon_mint).await;
+    assert_eq!(pokemon.trainer, ash.pubkey());
+    assert_eq!(pokemon.species_id, PIKACHU);
+
+    // A single Token-2022 NFT in the trainer's wallet, and no way to mint another
+    let mint_account = game.context.banks_client.get_account(pokemon_mint).await.unwrap().unwrap();
+    assert_eq!(mint_account.owner, spl_token_2022::ID);
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data).unwrap().base;
+    assert_eq!(mint.supply, 1);
+    assert!(mint.mint_authority.is_none());
+    assert_eq!(game.token_balance(game.pokemon_account(&ash.pubkey(), &pokemon_mint)).await, 1);
+}
+
+#[tokio::test]
+async fn list_and_buy_pokemon_spl_token() {
+    list_and_buy_pokemon(spl_token::ID).await;
+}
+
+#[tokio::test]
+async fn list_and_buy_pokemon_token_2022() {
+    list_and_buy_pokemon(spl_token_2022::ID).await;
+}
+
+async fn list_and_buy_pokemon(coin_program: Pubkey) {
+    let mut game = Game::start(coin_program).await;
+    let seller = game.register_trainer("misty").await;
+    let buyer = game.register_trainer("brock").await;
+    let pokemon_mint = game.catch_pokemon(&seller, PIKACHU, "Pikachu").await;
+
+    let listing = pda(&[b"listing", pokemon_mint.as_ref()]);
+    let escrow_pokemon_account = game.pokemon_account(&listing, &pokemon_mint);
+    let price = 100 * COIN;
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::ListPokemon {
+            listing,
+            pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+            pokemon_mint,
+            seller_pokemon_account: game.pokemon_account(&seller.pubkey(), &pokemon_mint),
+            escrow_pokemon_account,
+            seller: seller.pubkey(),
+            token_program: spl_token_2022::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::ListPokemon { price }.data(),
+    }, &[&seller]).await.unwrap();
+    assert_eq!(game.token_balance(escrow_pokemon_account).await, 1);
+    assert!(game.pokemon(&pokemon_mint).await.status == PokemonStatus::Listed);
+
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts: pokemon_game::accounts::BuyPokemon {
+            listing,
+            pokemon: pda(&[b"pokemon", pokemon_mint.as_ref()]),
+            pokemon_mint,
+            game_state: pda(&[b"game_state"]),
+            escrow_pokemon_account,
+            buyer_pokemon_account: game.pokemon_account(&buyer.pubkey(), &pokemon_mint),
+            buyer_trainer: pda(&[b"trainer", buyer.pubkey().as_ref()]),
+            seller_trainer: pda(&[b"trainer", seller.pubkey().as_ref()]),
+            poke_coin_mint: game.poke_coin_mint,
+            buyer_coin_account: game.coin_account(&buyer.pubkey()),
+            seller_coin_account: game.coin_account(&seller.pubkey()),
+            treasury_coin_account: game.treasury,
+            seller: seller.pubkey(),
+            buyer: buyer.pubkey(),
+            token_program: spl_token_2022::ID,
+            coin_token_program: coin_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: pokemon_game::instruction::BuyPokemon {}.data(),
+    }, &[&buyer]).await.unwrap();
+
+    // The seller gets the price minus the 2.5% marketplace fee, which goes to the treasury
+    let fee = price * 250 / 10_000;
+    assert_eq!(game.token_balance(game.coin_account(&buyer.pubkey())).await, 1000 * COIN - price);
+    assert_eq!(game.token_balance(game.coin_account(&seller.pubkey())).await, 1000 * COIN + price - fee);
+    assert_eq!(game.token_balance(game.treasury).await, fee);
+
+    assert_eq!(game.token_balance(game.pokemon_account(&buyer.pubkey(), &pokemon_mint)).await, 1);
+    let pokemon = game.pokemon(&pokemon_mint).await;
+    assert_eq!(pokemon.trainer, buyer.pubkey());
+    assert!(pokemon.status == PokemonStatus::Active);
+    assert!(game.context.banks_client.get_account(escrow_pokemon_account).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn outbid_refund_with_transfer_fee() {
+    // 1% of every PokeCoin transfer is withheld by the mint
+    let mut game = Game::start_with_transfer_fee(100).await;
+    let seller = game.register_trainer("misty").await;
+    let first = game.register_trainer("brock").await;
+    let second = game.register_trainer("ash").await;
+    let pokemon_mint = game.catch_pokemon(&seller, PIKACHU, "Pikachu").await;
+    game.create_auction(&seller, pokemon_mint, 100 * COIN, 60 * 60).await;
+
+    let bid_vault = game.coin_account(&pda(&[b"auction", pokemon_mint.as_ref()]));
+    game.place_bid(&first, pokemon_mint, 100 * COIN, None).await.unwrap();
+    let escrowed = 100 * COIN - game.transfer_fee(100 * COIN);
+    assert_eq!(game.token_balance(bid_vault).await, escrowed);
+
+    // The outbid bidder gets back what the vault holds, less the fee on the way out
+    game.place_bid(&second, pokemon_mint, 120 * COIN, Some(first.pubkey())).await.unwrap();
+    assert_eq!(
+        game.token_balance(game.coin_account(&first.pubkey())).await,
+        1000 * COIN - 100 * COIN + escrowed - game.transfer_fee(escrowed)
+    );
+    assert_eq!(game.token_balance(bid_vault).await, 120 * COIN - game.transfer_fee(120 * COIN));
+}
+
+#[tokio::test]
+async fn trade_pokemon_spl_token() {
+    trade_pokemon(Game::start(spl_token::ID).await).await;
+}
+
+#[tokio::test]
+async fn trade_pokemon_token_2022() {
+    trade_pokemon(Game::start(spl_token_2022::ID).await).await;
+}
+
+#[tokio::test]
+async fn trade_pokemon_with_transfer_fee() {
+    trade_pokemon(Game::start_with_transfer_fee(100).await).await;
+}
+
+async fn trade_pokemon(mut game: Game) {
+    let coin_program = game.coin_program;
+    let proposer = game.register_trainer("gary").await;
+    let recipient = game.register_trainer("ash").await;
+    let offered_mint = game.catch_pokemon(&proposer, EEVEE, "Eevee").await;
+    let requested_mint = game.catch_pokemon(&recipient, PIKACHU, "Pikachu").await;
+
+    let trade = pda(&[b"trade", proposer.pubkey().as_ref(), recipient.pubkey().as_ref()]);
+    let coin_vault = game.coin_account(&trade);
+    let escrow_pokemon_account = game.pokemon_account(&trade, &offered_mint);
+    let coin_amount = 50 * COIN;
+
+    let mut accounts = pokemon_game::accounts::ProposeTrade {
+        trade,
+        recipient_trainer: pda(&[b"trainer", recipient.pubkey().as_ref()]),
+        recipient: recipient.pubkey(),
+        game_state: pda(&[b"game_state"]),
+        poke_coin_mint: game.poke_coin_mint,
+        proposer_coin_account: game.coin_account(&proposer.pubkey()),
+        coin_vault,
+        proposer: proposer.pubkey(),
+        token_program: spl_token_2022::ID,
+        coin_token_program: coin_program,
+        associated_token_program: anchor_spl::associated_token::ID,
+        system_program: system_program::ID,
+    }.to_account_metas(None);
+    accounts.extend([
+        AccountMeta::new(pda(&[b"pokemon", offered_mint.as_ref()]), false),
+        AccountMeta::new_readonly(offered_mint, false),
+        AccountMeta::new(game.pokemon_account(&proposer.pubkey(), &offered_mint), false),
+        AccountMeta::new(escrow_pokemon_account, false),
+    ]);
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts,
+        data: pokemon_game::instruction::ProposeTrade {
+            offered_mints: vec![offered_mint],
+            requested_mints: vec![requested_mint],
+            coin_amount,
+        }.data(),
+    }, &[&proposer]).await.unwrap();
+    assert_eq!(game.token_balance(escrow_pokemon_account).await, 1);
+    let escrowed = coin_amount - game.transfer_fee(coin_amount);
+    assert_eq!(game.token_balance(coin_vault).await, escrowed);
+
+    let mut accounts = pokemon_game::accounts::AcceptTrade {
+        trade,
+        proposer_trainer: pda(&[b"trainer", proposer.pubkey().as_ref()]),
+        recipient_trainer: pda(&[b"trainer", recipient.pubkey().as_ref()]),
+        game_state: pda(&[b"game_state"]),
+        poke_coin_mint: game.poke_coin_mint,
+        coin_vault,
+        recipient_coin_account: game.coin_account(&recipient.pubkey()),
+        proposer: proposer.pubkey(),
+        recipient: recipient.pubkey(),
+        token_program: spl_token_2022::ID,
+        coin_token_program: coin_program,
+        associated_token_program: anchor_spl::associated_token::ID,
+        system_program: system_program::ID,
+    }.to_account_metas(None);
+    accounts.extend([
+        AccountMeta::new(pda(&[b"pokemon", offered_mint.as_ref()]), false),
+        AccountMeta::new_readonly(offered_mint, false),
+        AccountMeta::new(escrow_pokemon_account, false),
+        AccountMeta::new(game.pokemon_account(&recipient.pubkey(), &offered_mint), false),
+        AccountMeta::new(pda(&[b"pokemon", requested_mint.as_ref()]), false),
+        AccountMeta::new_readonly(requested_mint, false),
+        AccountMeta::new(game.pokemon_account(&recipient.pubkey(), &requested_mint), false),
+        AccountMeta::new(game.pokemon_account(&proposer.pubkey(), &requested_mint), false),
+    ]);
+    game.send(Instruction {
+        program_id: pokemon_game::ID,
+        accounts,
+        data: pokemon_game::instruction::AcceptTrade {}.data(),
+    }, &[&recipient]).await.unwrap();
+
+    // Both Pokemon and the offered PokeCoin changed hands, and the escrow is closed
+    assert_eq!(game.token_balance(game.pokemon_account(&recipient.pubkey(), &offered_mint)).await, 1);
+    assert_eq!(game.token_balance(game.pokemon_account(&proposer.pubkey(), &requested_mint)).await, 1);
+    assert_eq!(game.pokemon(&offered_mint).await.trainer, recipient.pubkey());
+    assert_eq!(game.pokemon(&requested_mint).await.trainer, proposer.pubkey());
+    assert_eq!(
+        game.token_balance(game.coin_account(&recipient.pubkey())).await,
+        1000 * COIN + escrowed - game.transfer_fee(escrowed)
+    );
+    assert_eq!(game.token_balance(game.coin_account(&proposer.pubkey())).await, 1000 * COIN - coin_amount);
+    assert!(game.context.banks_client.get_account(trade).await.unwrap().is_none());
+    assert!(game.context.banks_client.get_account(coin_vault).await.unwrap().is_none());
+}
@@ -64,10 +64,17 @@ interface ProgramPokemon {
   bump: number;
 }
 
+// Evento PokemonObtained que emite el programa al abrir un pack
+interface PokemonObtainedEvent {
+  packs_until_guaranteed: number;
+}
+
 // Mock hook para desarrollo
 const useMockPokemonProgram = () => {
   const [trainer, setTrainer] = useState<Trainer | null>(null)
   const [userPokemon, setUserPokemon] = useState<ProgramPokemon[]>([])
+  // Último `packs_until_guaranteed` recibido en un evento PokemonObtained
+  const [packsUntilGuaranteed, setPacksUntilGuaranteed] = useState<number | undefined>(undefined)
   const [loading, setLoading] = useState(false)
   const [error, setError] = useState<string | null>(null)
 
@@ -221,12 +228,18 @@ const useMockPokemonProgram = () => {
     return packPokemon
   }
 
+  const handlePokemonObtained = (event: PokemonObtainedEvent) => {
+    setPacksUntilGuaranteed(event.packs_until_guaranteed)
+  }
+
   return {
     trainer,
     userPokemon,
     registerTrainer,
     catchPokemon,
     openBoosterPack,
+    packsUntilGuaranteed,
+    handlePokemonObtained,
     challengeTrainer,
     loading,
     error
@@ -262,6 +275,7 @@ export default function HomePage() {
     registerTrainer, 
     catchPokemon, 
     openBoosterPack,
+    packsUntilGuaranteed,
     challengeTrainer,
     loading,
     error 
@@ -1129,6 +1143,11 @@ export default function HomePage() {
                       </div>
                     </div>
                     <div className="text-3xl font-bold text-green-400 mb-6">50 USDC</div>
+                    {packsUntilGuaranteed !== undefined && (
+                      <div className="text-purple-300 text-sm font-bold mb-4">
+                        👑 {packsUntilGuaranteed} {packsUntilGuaranteed === 1 ? 'pack' : 'packs'} until guaranteed Legendary
+                      </div>
+                    )}
                     <motion.button
                       whileHover={{ scale: 1.05 }}
                       whileTap={{ scale: 0.95 }}
@@ -11,7 +11,12 @@ interface CatchResult {
   isLegendary: boolean;
 }
 
-const BoosterPack = () => {
+interface BoosterPackProps {
+  // Viene de `packs_until_guaranteed` en el evento PokemonObtained del programa
+  packsUntilGuaranteed?: number;
+}
+
+const BoosterPack = ({ packsUntilGuaranteed }: BoosterPackProps) => {
   const [isProcessing, setIsProcessing] = useState(false);
   const [catchResult, setCatchResult] = useState<CatchResult | null>(null);
   const { addPokemon, updateStats } = useGameStore();
@@ -97,6 +102,12 @@ const BoosterPack = () => {
           Click to catch a random Pokémon from the wild!
         </p>
         
+        {packsUntilGuaranteed !== undefined && (
+          <p className="text-yellow-300 text-center text-sm mb-4">
+            👑 {packsUntilGuaranteed} {packsUntilGuaranteed === 1 ? 'pack' : 'packs'} until guaranteed Legendary
+          </p>
+        )}
+        
         <button
           onClick={handleCatchPokemon}
           disabled={isProcessing}